@@ -1,59 +1,137 @@
+use advent_of_code::parse::{self, ParseError};
+
 advent_of_code::solution!(2);
 
-pub fn part_one(input: &str) -> Option<u32> {
-    let mut successes = 0;
-    for numbers in input.lines().map(|line| {
-        line.split_whitespace()
-            .map(|number_str| number_str.parse::<u32>().unwrap())
-            .collect::<Vec<_>>()
-    }) {
-        let differences = numbers
-            .windows(2)
-            .map(|window| window[0] as i64 - window[1] as i64)
-            .collect::<Vec<_>>();
-        if differences.iter().find(|num| num.abs() > 3).is_none()
-            && (differences.iter().all(|num| *num > 0) || differences.iter().all(|num| *num < 0))
-        {
-            successes += 1
-        }
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Trend {
+    Increasing,
+    Decreasing,
+}
+
+impl Trend {
+    fn step_ok(&self, from: u32, to: u32, max_step: u32) -> bool {
+        let step = match self {
+            Trend::Increasing => to.checked_sub(from),
+            Trend::Decreasing => from.checked_sub(to),
+        };
+        step.is_some_and(|step| (1..=max_step).contains(&step))
     }
-    Some(successes)
 }
 
-pub fn part_two(input: &str) -> Option<u32> {
-    let mut successes = 0;
-    for numbers in input.lines().map(|line| {
-        line.split_whitespace()
-            .map(|number_str| number_str.parse::<u32>().unwrap())
-            .collect::<Vec<_>>()
-    }) {
-        let differences = numbers
-            .windows(2)
-            .map(|window| window[0] as i64 - window[1] as i64)
-            .collect::<Vec<_>>();
-        if differences.iter().find(|num| num.abs() > 3).is_none()
-            && (differences.iter().all(|num| *num > 0) || differences.iter().all(|num| *num < 0))
-        {
-            successes += 1
-        } else {
-            for idx in 0..numbers.len() {
-                let mut removed = numbers.clone();
-                removed.remove(idx);
-                let differences = removed
+/// Which levels to drop, as indices into the original report, to leave a safe report.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Repair {
+    pub trend: Trend,
+    pub removed: Vec<usize>,
+}
+
+/// A report is safe when its levels strictly increase or decrease by at most `max_step` each time,
+/// and `tolerance` is how many levels may be removed to get there.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Analyzer {
+    pub max_step: u32,
+    pub tolerance: usize,
+}
+
+impl Analyzer {
+    pub fn new(max_step: u32, tolerance: usize) -> Analyzer {
+        Analyzer {
+            max_step,
+            tolerance,
+        }
+    }
+
+    /// The trend of a safe report, increasing is preferred for reports too short to tell.
+    pub fn classify(&self, levels: &[u32]) -> Option<Trend> {
+        [Trend::Increasing, Trend::Decreasing]
+            .into_iter()
+            .find(|trend| {
+                levels
                     .windows(2)
-                    .map(|window| window[0] as i64 - window[1] as i64)
-                    .collect::<Vec<_>>();
-                if differences.iter().find(|num| num.abs() > 3).is_none()
-                    && (differences.iter().all(|num| *num > 0)
-                        || differences.iter().all(|num| *num < 0))
+                    .all(|pair| trend.step_ok(pair[0], pair[1], self.max_step))
+            })
+    }
+
+    /// The fewest removals that make the report safe, if no more than `tolerance`. Each level only
+    /// looks back at the `tolerance + 1` levels before it, so this is linear for a fixed tolerance.
+    pub fn repair(&self, levels: &[u32]) -> Option<Repair> {
+        [Trend::Increasing, Trend::Decreasing]
+            .into_iter()
+            .filter_map(|trend| self.repair_with(levels, trend))
+            .min_by_key(|repair| repair.removed.len())
+    }
+
+    fn repair_with(&self, levels: &[u32], trend: Trend) -> Option<Repair> {
+        let n = levels.len();
+        if n == 0 {
+            return Some(Repair {
+                trend,
+                removed: vec![],
+            });
+        }
+        // removals[i] is the fewest levels removed before `i` when `i` is kept, from `previous`.
+        let mut removals: Vec<Option<usize>> = vec![None; n];
+        let mut previous: Vec<Option<usize>> = vec![None; n];
+        for i in 0..n {
+            if i <= self.tolerance {
+                removals[i] = Some(i);
+            }
+            for j in i.saturating_sub(self.tolerance + 1)..i {
+                let Some(before) = removals[j] else {
+                    continue;
+                };
+                let cost = before + (i - j - 1);
+                if cost <= self.tolerance
+                    && removals[i].is_none_or(|best| cost < best)
+                    && trend.step_ok(levels[j], levels[i], self.max_step)
                 {
-                    successes += 1;
-                    break;
+                    removals[i] = Some(cost);
+                    previous[i] = Some(j);
                 }
             }
         }
+        let last = (n - 1 - self.tolerance.min(n - 1)..n)
+            .filter_map(|i| removals[i].map(|cost| (cost + (n - 1 - i), i)))
+            .filter(|(cost, _)| *cost <= self.tolerance)
+            .min()?
+            .1;
+        let mut kept = vec![false; n];
+        let mut current = Some(last);
+        while let Some(i) = current {
+            kept[i] = true;
+            current = previous[i];
+        }
+        Some(Repair {
+            trend,
+            removed: (0..n).filter(|i| !kept[*i]).collect(),
+        })
     }
-    Some(successes)
+
+    pub fn is_safe(&self, levels: &[u32]) -> bool {
+        self.repair(levels).is_some()
+    }
+}
+
+fn parse(input: &str) -> Result<Vec<Vec<u32>>, ParseError> {
+    parse::lines(input).map(|line| line.integers()).collect()
+}
+
+fn count_safe(input: &str, analyzer: Analyzer) -> Option<u32> {
+    let reports = parse(input).ok()?;
+    Some(
+        reports
+            .iter()
+            .filter(|report| analyzer.is_safe(report))
+            .count() as u32,
+    )
+}
+
+pub fn part_one(input: &str) -> Option<u32> {
+    count_safe(input, Analyzer::new(3, 0))
+}
+
+pub fn part_two(input: &str) -> Option<u32> {
+    count_safe(input, Analyzer::new(3, 1))
 }
 
 #[cfg(test)]
@@ -71,4 +149,48 @@ mod tests {
         let result = part_two(&advent_of_code::template::read_file("examples", DAY));
         assert_eq!(result, Some(4));
     }
+
+    #[test]
+    fn test_classify_and_repair() {
+        let analyzer = Analyzer::new(3, 1);
+        assert_eq!(analyzer.classify(&[7, 6, 4, 2, 1]), Some(Trend::Decreasing));
+        assert_eq!(analyzer.classify(&[1, 3, 2, 4, 5]), None);
+        // Either the 3 or the 2 can go, the repair must leave a safe report.
+        let repair = analyzer.repair(&[1, 3, 2, 4, 5]).unwrap();
+        assert_eq!(repair.trend, Trend::Increasing);
+        assert_eq!(repair.removed.len(), 1);
+        let mut repaired = vec![1, 3, 2, 4, 5];
+        repaired.remove(repair.removed[0]);
+        assert_eq!(analyzer.classify(&repaired), Some(Trend::Increasing));
+        assert_eq!(
+            analyzer
+                .repair(&[8, 6, 4, 4, 1])
+                .map(|repair| repair.removed),
+            Some(vec![3])
+        );
+        assert_eq!(analyzer.repair(&[1, 2, 7, 8, 9]), None);
+        // Dropping the first or last level is allowed too.
+        assert_eq!(
+            analyzer.repair(&[9, 1, 2, 3]).map(|repair| repair.removed),
+            Some(vec![0])
+        );
+        assert_eq!(
+            analyzer.repair(&[1, 2, 3, 0]).map(|repair| repair.removed),
+            Some(vec![3])
+        );
+
+        let lenient = Analyzer::new(5, 2);
+        assert_eq!(lenient.repair(&[1, 2, 9, 10, 20, 30, 40]), None);
+        assert_eq!(
+            lenient
+                .repair(&[1, 20, 2, 7, 30, 8])
+                .map(|repair| repair.removed),
+            Some(vec![1, 4])
+        );
+        assert_eq!(
+            lenient.repair(&[1, 2]).map(|repair| repair.removed),
+            Some(vec![])
+        );
+        assert!(Analyzer::new(3, 0).is_safe(&[]));
+    }
 }
@@ -2,55 +2,114 @@ use regex::Regex;
 
 advent_of_code::solution!(3);
 
-pub fn part_one_regex(input: &str) -> Option<u32> {
-    let re = Regex::new(r"mul\(([0-9]+),([0-9]+)\)").unwrap();
-    let mut total = 0;
-    for group in re.captures_iter(input) {
-        let (_, [first, second]) = group.extract();
-        total += first.parse::<u32>().unwrap() * second.parse::<u32>().unwrap();
-    }
-    Some(total)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Token {
+    Mul(u32, u32),
+    Do,
+    Dont,
 }
-const TERMS: [char; 4] = ['m', 'u', 'l', '('];
 
-pub fn part_one(input: &str) -> Option<u32> {
-    part_one_regex(input)
+/// Streams `mul(a,b)`, `do()` and `don't()` tokens with their byte offsets, skipping everything
+/// else without allocating.
+pub struct Tokens<'a> {
+    bytes: &'a [u8],
+    pos: usize,
 }
 
-pub fn part_one_parser(input: &str) -> Option<u32> {
-    let mut characters = input.chars().peekable();
-    let mut total = 0;
-    let mut pos = 0;
-    let mut buf = String::new();
-    while let Some(ch) = characters.next() {
-        if pos == TERMS.len() {
-            if ch == ')' {
-                if let Some((first, last)) = buf.split_once(',') {
-                    total += first.parse::<u32>().unwrap() * last.parse::<u32>().unwrap();
-                    buf.clear();
-                    pos = 0;
-                } else {
-                    buf.clear();
-                    pos = 0;
-                }
-            } else if ch.is_ascii_digit() || ch == ',' {
-                buf.push(ch);
-            } else {
-                buf.clear();
-                pos = 0;
+impl<'a> Tokens<'a> {
+    pub fn new(bytes: &'a [u8]) -> Tokens<'a> {
+        Tokens { bytes, pos: 0 }
+    }
+
+    /// Reads a 1 to 3 digit operand followed by `end`.
+    fn operand(&self, mut pos: usize, end: u8) -> Option<(u32, usize)> {
+        let mut value = 0;
+        let start = pos;
+        while let Some(digit) = self.bytes.get(pos).filter(|byte| byte.is_ascii_digit()) {
+            if pos - start == 3 {
+                return None;
             }
-        }
-        if pos != TERMS.len() && ch == TERMS[pos] {
+            value = value * 10 + (digit - b'0') as u32;
             pos += 1;
         }
+        if pos > start && self.bytes.get(pos) == Some(&end) {
+            Some((value, pos + 1))
+        } else {
+            None
+        }
+    }
+
+    fn token_at(&self, pos: usize) -> Option<(Token, usize)> {
+        let rest = &self.bytes[pos..];
+        if rest.starts_with(b"mul(") {
+            let (first, pos) = self.operand(pos + 4, b',')?;
+            let (second, pos) = self.operand(pos, b')')?;
+            Some((Token::Mul(first, second), pos))
+        } else if rest.starts_with(b"do()") {
+            Some((Token::Do, pos + 4))
+        } else if rest.starts_with(b"don't()") {
+            Some((Token::Dont, pos + 7))
+        } else {
+            None
+        }
+    }
+}
+
+impl Iterator for Tokens<'_> {
+    type Item = (usize, Token);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.pos < self.bytes.len() {
+            let start = self.pos;
+            match self.bytes[start] {
+                b'm' | b'd' => match self.token_at(start) {
+                    Some((token, end)) => {
+                        self.pos = end;
+                        return Some((start, token));
+                    }
+                    None => self.pos += 1,
+                },
+                _ => self.pos += 1,
+            }
+        }
+        None
     }
-    Some(total)
+}
+
+pub fn part_one(input: &str) -> Option<u32> {
+    Some(
+        Tokens::new(input.as_bytes()).fold(0, |total, (_, token)| match token {
+            Token::Mul(first, second) => total + first * second,
+            _ => total,
+        }),
+    )
 }
 
 pub fn part_two(input: &str) -> Option<u32> {
+    let (total, _) =
+        Tokens::new(input.as_bytes()).fold((0, true), |(total, enabled), (_, token)| match token {
+            Token::Mul(first, second) if enabled => (total + first * second, enabled),
+            Token::Mul(_, _) => (total, enabled),
+            Token::Do => (total, true),
+            Token::Dont => (total, false),
+        });
+    Some(total)
+}
+
+pub fn part_one_regex(input: &str) -> Option<u32> {
+    let re = Regex::new(r"mul\(([0-9]{1,3}),([0-9]{1,3})\)").unwrap();
+    let mut total = 0;
+    for group in re.captures_iter(input) {
+        let (_, [first, second]) = group.extract();
+        total += first.parse::<u32>().unwrap() * second.parse::<u32>().unwrap();
+    }
+    Some(total)
+}
+
+pub fn part_two_regex(input: &str) -> Option<u32> {
     let re = Regex::new(r"don't\(\)((.|\n)*?)(do\(\)|\z)").unwrap();
     let replaced = re.replace_all(input, "");
-    part_one(&replaced)
+    part_one_regex(&replaced)
 }
 
 #[cfg(test)]
@@ -90,4 +149,45 @@ mod tests {
         let result = part_two("don't()\nmul(1,2)");
         assert_eq!(result, Some(0));
     }
+    #[test]
+    fn test_tokens() {
+        let tokens =
+            Tokens::new(b"mmul(1,22)mul(1234,1)do()don't()mul(2,3]mul(123,4)").collect::<Vec<_>>();
+        assert_eq!(
+            tokens,
+            vec![
+                (1, Token::Mul(1, 22)),
+                (21, Token::Do),
+                (25, Token::Dont),
+                (40, Token::Mul(123, 4))
+            ]
+        );
+    }
+
+    /// Compare against the regex implementation with
+    /// `cargo test --release --bin 03 bench -- --ignored --nocapture`.
+    #[test]
+    #[ignore]
+    fn bench_tokens_against_regex() {
+        use std::time::Instant;
+
+        let example = advent_of_code::template::read_file("examples", DAY);
+        let input = format!("{example}don't()mul(5,5)do()").repeat(10_000);
+        for (name, tokens, regex) in [
+            (
+                "part one",
+                part_one as fn(&str) -> Option<u32>,
+                part_one_regex as fn(&str) -> Option<u32>,
+            ),
+            ("part two", part_two, part_two_regex),
+        ] {
+            let timer = Instant::now();
+            let expected = regex(&input);
+            let regex_time = timer.elapsed();
+            let timer = Instant::now();
+            assert_eq!(tokens(&input), expected);
+            let tokens_time = timer.elapsed();
+            println!("{name}: tokens {tokens_time:?}, regex {regex_time:?}");
+        }
+    }
 }
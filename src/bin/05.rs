@@ -1,110 +1,172 @@
-use std::collections::HashMap;
+use std::fmt::Display;
+
+use advent_of_code::parse::{sections, ParseError};
+use petgraph::{algo::tarjan_scc, graphmap::DiGraphMap};
 
 advent_of_code::solution!(5);
 
-pub fn part_one(input: &str) -> Option<u32> {
-    let mut page_ordering_rules_before: HashMap<u32, Vec<u32>> = HashMap::new();
-    let mut input_lines = input.lines();
-    while let Some(line) = input_lines.next() {
-        if line.is_empty() {
-            break;
-        } else {
-            if let Some((must_be_before_str, number_str)) = line.split_once("|") {
-                let must_be_after: u32 = number_str.parse().unwrap();
-                let must_be_before: u32 = must_be_before_str.parse().unwrap();
-                if let Some(before) = page_ordering_rules_before.get_mut(&must_be_after) {
-                    before.push(must_be_before);
-                } else {
-                    page_ordering_rules_before.insert(must_be_after, vec![must_be_before]);
-                }
+type Rules = Vec<(u32, u32)>;
+type Updates = Vec<Vec<u32>>;
+
+/// Pages are numbered below this, so a page's successors fit in one u128.
+pub const MAX_PAGES: u32 = 100;
+
+fn parse(input: &str) -> Result<(Rules, Updates), ParseError> {
+    let sections = sections(input);
+    let rules = sections.first().map_or(Ok(vec![]), |section| {
+        section.lines().iter().map(|line| line.pair("|")).collect()
+    })?;
+    let updates = sections.get(1).map_or(Ok(vec![]), |section| {
+        section
+            .lines()
+            .iter()
+            .map(|line| line.separated(","))
+            .collect()
+    })?;
+    Ok((rules, updates))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RulesError {
+    PageOutOfRange(u32),
+}
+
+impl Display for RulesError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RulesError::PageOutOfRange(page) => {
+                write!(f, "page {page} is not below {MAX_PAGES}")
             }
         }
     }
-    let mut total: u32 = 0;
-    while let Some(line) = input_lines.next() {
-        let pages = line
-            .split(",")
-            .map(|page| page.parse::<u32>().unwrap())
-            .collect::<Vec<_>>();
-        let mut valid = true;
-        for (i, page) in pages.iter().enumerate() {
-            if let Some(before_rules) = page_ordering_rules_before.get(&page) {
-                for other in pages.get(0..i).unwrap() {
-                    valid = valid && before_rules.contains(other);
-                }
-                for other in pages.get(i..pages.len()).unwrap() {
-                    valid = valid && !before_rules.contains(other);
-                }
-            }
-        }
-        if valid {
-            total += pages[pages.len() / 2];
-        }
+}
+
+impl std::error::Error for RulesError {}
+
+/// The first rule an update breaks, with where the two pages were found.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Violation {
+    pub rule: (u32, u32),
+    /// Positions of `rule.1` and `rule.0` in the update, the later page came first.
+    pub positions: (usize, usize),
+}
+
+impl Display for Violation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let (before, after) = self.rule;
+        write!(
+            f,
+            "rule {before}|{after} is broken, {after} is at position {} but {before} only at {}",
+            self.positions.0, self.positions.1
+        )
     }
-    Some(total)
 }
 
-pub fn part_two(input: &str) -> Option<u32> {
-    let mut page_ordering_rules_before: HashMap<u32, Vec<u32>> = HashMap::new();
-    let mut input_lines = input.lines();
-    while let Some(line) = input_lines.next() {
-        if line.is_empty() {
-            break;
-        } else {
-            if let Some((must_be_before_str, number_str)) = line.split_once("|") {
-                let must_be_after: u32 = number_str.parse().unwrap();
-                let must_be_before: u32 = must_be_before_str.parse().unwrap();
-                if let Some(before) = page_ordering_rules_before.get_mut(&must_be_after) {
-                    before.push(must_be_before);
-                } else {
-                    page_ordering_rules_before.insert(must_be_after, vec![must_be_before]);
+/// Page ordering rules as a precedence bit matrix, bit `b` of `before[a]` means `a|b`.
+pub struct OrderingRules {
+    before: Vec<u128>,
+    rules: Rules,
+}
+
+impl OrderingRules {
+    pub fn new(rules: &[(u32, u32)]) -> Result<OrderingRules, RulesError> {
+        let mut before = vec![0_u128; MAX_PAGES as usize];
+        for (first, second) in rules {
+            for page in [first, second] {
+                if *page >= MAX_PAGES {
+                    return Err(RulesError::PageOutOfRange(*page));
                 }
             }
+            before[*first as usize] |= 1 << second;
         }
+        Ok(OrderingRules {
+            before,
+            rules: rules.to_vec(),
+        })
     }
-    let mut invalid: Vec<Vec<u32>> = Vec::new();
-    while let Some(line) = input_lines.next() {
-        let pages = line
-            .split(",")
-            .map(|page| page.parse::<u32>().unwrap())
-            .collect::<Vec<_>>();
-        let mut valid = true;
-        for (i, page) in pages.iter().enumerate() {
-            if let Some(before_rules) = page_ordering_rules_before.get(&page) {
-                for other in pages.get(0..i).unwrap() {
-                    valid = valid && before_rules.contains(other);
-                }
-                for other in pages.get(i..pages.len()).unwrap() {
-                    valid = valid && !before_rules.contains(other);
-                }
-            }
-        }
-        if !valid {
-            invalid.push(pages);
-        }
+
+    /// Whether a rule says `first` must come before `second`. Pages out of range have no rules.
+    pub fn precedes(&self, first: u32, second: u32) -> bool {
+        first < MAX_PAGES && second < MAX_PAGES && self.before[first as usize] & (1 << second) != 0
+    }
+
+    pub fn violation(&self, update: &[u32]) -> Option<Violation> {
+        update.iter().enumerate().find_map(|(i, page)| {
+            update[i + 1..]
+                .iter()
+                .position(|later| self.precedes(*later, *page))
+                .map(|offset| Violation {
+                    rule: (update[i + 1 + offset], *page),
+                    positions: (i, i + 1 + offset),
+                })
+        })
     }
 
-    let mut total = 0;
-    for mut pages in invalid {
-        loop {
-            let mut changed = false;
-            for pos in 0..(pages.len() - 1) {
-                let page = pages[pos];
-                let page_two = pages[pos + 1];
-                if let Some(before_rules) = page_ordering_rules_before.get(&page) {
-                    if before_rules.contains(&page_two) {
-                        pages.swap(pos, pos + 1);
-                        changed = true;
-                    }
+    pub fn is_sorted(&self, update: &[u32]) -> bool {
+        self.violation(update).is_none()
+    }
+
+    /// Topologically sorts the update using only the rules between its own pages, keeping the
+    /// original order where the rules don't decide. `None` if those rules form a cycle.
+    pub fn sort(&self, update: &[u32]) -> Option<Vec<u32>> {
+        let mut blockers: Vec<usize> = update
+            .iter()
+            .map(|page| {
+                update
+                    .iter()
+                    .filter(|other| self.precedes(**other, *page))
+                    .count()
+            })
+            .collect();
+        let mut placed = vec![false; update.len()];
+        let mut sorted = Vec::with_capacity(update.len());
+        while sorted.len() < update.len() {
+            let next = (0..update.len()).find(|i| !placed[*i] && blockers[*i] == 0)?;
+            placed[next] = true;
+            sorted.push(update[next]);
+            for (i, page) in update.iter().enumerate() {
+                if self.precedes(update[next], *page) {
+                    blockers[i] -= 1;
                 }
             }
-            if !changed {
-                total += pages[pages.len() / 2];
-                break;
-            }
         }
+        Some(sorted)
+    }
+
+    /// A set of pages whose rules form a cycle across the whole rule set, if there is one. The
+    /// puzzle's rules usually do, which is why sorting only looks at the pages in an update.
+    pub fn find_cycle(&self) -> Option<Vec<u32>> {
+        let graph: DiGraphMap<u32, ()> = DiGraphMap::from_edges(&self.rules);
+        tarjan_scc(&graph).into_iter().find(|component| {
+            component.len() > 1 || graph.contains_edge(component[0], component[0])
+        })
     }
-    Some(total)
+}
+
+fn middle(update: &[u32]) -> u32 {
+    update[update.len() / 2]
+}
+
+pub fn part_one(input: &str) -> Option<u32> {
+    let (rules, updates) = parse(input).ok()?;
+    let rules = OrderingRules::new(&rules).ok()?;
+    Some(
+        updates
+            .iter()
+            .filter(|update| rules.is_sorted(update))
+            .map(|update| middle(update))
+            .sum(),
+    )
+}
+
+pub fn part_two(input: &str) -> Option<u32> {
+    let (rules, updates) = parse(input).ok()?;
+    let rules = OrderingRules::new(&rules).ok()?;
+    updates
+        .iter()
+        .filter(|update| !rules.is_sorted(update))
+        .map(|update| rules.sort(update).map(|sorted| middle(&sorted)))
+        .sum()
 }
 
 #[cfg(test)]
@@ -122,4 +184,35 @@ mod tests {
         let result = part_two(&advent_of_code::template::read_file("examples", DAY));
         assert_eq!(result, Some(123));
     }
+
+    #[test]
+    fn test_ordering_rules() {
+        let input = advent_of_code::template::read_file("examples", DAY);
+        let (rules, _) = parse(&input).unwrap();
+        let rules = OrderingRules::new(&rules).unwrap();
+        assert_eq!(rules.find_cycle(), None);
+        assert!(rules.is_sorted(&[75, 47, 61, 53, 29]));
+        let violation = rules.violation(&[75, 97, 47, 61, 53]).unwrap();
+        assert_eq!(violation.rule, (97, 75));
+        assert_eq!(violation.positions, (0, 1));
+        assert_eq!(
+            violation.to_string(),
+            "rule 97|75 is broken, 75 is at position 0 but 97 only at 1"
+        );
+        assert_eq!(
+            rules.sort(&[97, 13, 75, 29, 47]),
+            Some(vec![97, 75, 47, 29, 13])
+        );
+
+        let cyclic = OrderingRules::new(&[(1, 2), (2, 3), (3, 1), (4, 5)]).unwrap();
+        let mut cycle = cyclic.find_cycle().unwrap();
+        cycle.sort();
+        assert_eq!(cycle, vec![1, 2, 3]);
+        assert_eq!(cyclic.sort(&[3, 2, 1]), None);
+        assert_eq!(cyclic.sort(&[2, 1]), Some(vec![1, 2]));
+        assert_eq!(
+            OrderingRules::new(&[(1, 100)]).err(),
+            Some(RulesError::PageOutOfRange(100))
+        );
+    }
 }
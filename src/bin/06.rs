@@ -1,132 +1,262 @@
-use std::collections::HashSet;
-
-use advent_of_code::XYWorld;
+use advent_of_code::{grid::GridError, XYWorld};
 
 advent_of_code::solution!(6);
 
-fn rotate_velocity(x: isize, y: isize) -> (isize, isize) {
-    match (x, y) {
-        (0, -1) => (1, 0),
-        (1, 0) => (0, 1),
-        (0, 1) => (-1, 0),
-        (-1, 0) => (0, -1),
-        _ => panic!(),
-    }
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Heading {
+    Up,
+    Right,
+    Down,
+    Left,
 }
 
-pub fn part_one(input: &str) -> Option<u32> {
-    let world: XYWorld<char> = XYWorld::<char>::from_str(input);
-    let (mut x, mut y) = world.find_first(&'^').expect("start point not found");
-    let (mut v_x, mut v_y) = (0_isize, -1_isize);
-    let mut path: HashSet<(usize, usize)> = HashSet::new();
-    path.insert((x, y));
-    loop {
-        let p_x = x.checked_add_signed(v_x).unwrap();
-        let p_y = y.checked_add_signed(v_y).unwrap();
-        if let Some(peek_ch) = world.get(p_x, p_y) {
-            if *peek_ch == '#' {
-                (v_x, v_y) = rotate_velocity(v_x, v_y);
-            } else {
-                x = p_x as usize;
-                y = p_y as usize;
-                path.insert((x.clone(), y.clone()));
-            }
-        } else {
-            break;
+impl Heading {
+    const ALL: [Heading; 4] = [Heading::Up, Heading::Right, Heading::Down, Heading::Left];
+
+    fn vector(&self) -> (isize, isize) {
+        match self {
+            Heading::Up => (0, -1),
+            Heading::Right => (1, 0),
+            Heading::Down => (0, 1),
+            Heading::Left => (-1, 0),
         }
     }
-    Some(path.len() as u32)
+
+    fn turn_right(&self) -> Heading {
+        Heading::ALL[(*self as usize + 1) % 4]
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Guard {
+    pub x: usize,
+    pub y: usize,
+    pub heading: Heading,
 }
 
-#[derive(Eq, PartialEq, Clone, Copy, Debug)]
-struct Guard {
-    x: usize,
-    y: usize,
-    x_v: isize,
-    y_v: isize,
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+    /// The guard walks off the map.
+    Exits,
+    /// The guard is stuck in a loop of `length` steps, first entered at `entry`, a point where the
+    /// guard has just turned.
+    Loops { length: usize, entry: Guard },
 }
 
-impl Guard {
-    fn new(x: usize, y: usize, x_v: isize, y_v: isize) -> Guard {
-        Guard { x, y, x_v, y_v }
+/// A set of guard states, one bit per `(cell, heading)`, which clears only what was inserted.
+struct StateSet {
+    bits: Vec<u64>,
+    touched: Vec<usize>,
+}
+
+impl StateSet {
+    fn new(states: usize) -> StateSet {
+        StateSet {
+            bits: vec![0; states.div_ceil(64)],
+            touched: vec![],
+        }
     }
 
-    fn rotate(&mut self) {
-        (self.x_v, self.y_v) = rotate_velocity(self.x_v, self.y_v);
+    /// Returns false if the state was already present.
+    fn insert(&mut self, state: usize) -> bool {
+        let (word, bit) = (state / 64, 1 << (state % 64));
+        if self.bits[word] & bit != 0 {
+            return false;
+        }
+        if self.bits[word] == 0 {
+            self.touched.push(word);
+        }
+        self.bits[word] |= bit;
+        true
     }
 
-    fn update(&mut self) -> Option<()> {
-        self.x = self.x.checked_add_signed(self.x_v)?;
-        self.y = self.y.checked_add_signed(self.y_v)?;
-        Some(())
+    fn clear(&mut self) {
+        for word in self.touched.drain(..) {
+            self.bits[word] = 0;
+        }
     }
 }
 
-//todo to increase the speed of the algorithm we simply need to on search along the path where a
-//the 90 degree position would hit another blocker.
-fn find_path(world: &XYWorld<char>) -> HashSet<(usize, usize)> {
-    let (mut x, mut y) = world.find_first(&'^').expect("start point not found");
-    let (mut v_x, mut v_y) = (0_isize, -1_isize);
-    let mut path: HashSet<(usize, usize)> = HashSet::new();
-    path.insert((x, y));
-    loop {
-        let p_x = x.checked_add_signed(v_x).unwrap();
-        let p_y = y.checked_add_signed(v_y).unwrap();
-        if let Some(peek_ch) = world.get(p_x, p_y) {
-            if *peek_ch == '#' {
-                (v_x, v_y) = rotate_velocity(v_x, v_y);
-            } else {
-                x = p_x as usize;
-                y = p_y as usize;
-                path.insert((x.clone(), y.clone()));
-            }
+pub struct Lab {
+    obstacles: XYWorld<bool>,
+    start: Guard,
+    /// For each heading and cell, the cell the guard stops on in front of the next obstacle, or
+    /// `None` if it walks off the map first.
+    jumps: [Vec<Option<usize>>; 4],
+}
+
+impl Lab {
+    pub fn parse(input: &str) -> Result<Lab, GridError> {
+        let (obstacles, markers) =
+            XYWorld::parse_with_markers(input, &[('^', false)], |ch| match ch {
+                '#' => Some(true),
+                '.' => Some(false),
+                _ => None,
+            })?;
+        let (x, y) = markers.require('^')?;
+        let start = Guard {
+            x,
+            y,
+            heading: Heading::Up,
+        };
+        let jumps = Heading::ALL.map(|heading| Lab::jump_table(&obstacles, heading));
+        Ok(Lab {
+            obstacles,
+            start,
+            jumps,
+        })
+    }
+
+    fn jump_table(obstacles: &XYWorld<bool>, heading: Heading) -> Vec<Option<usize>> {
+        let (width, height) = (obstacles.width, obstacles.height);
+        let (dx, dy) = heading.vector();
+        let mut jumps = vec![None; width * height];
+        // Visit cells furthest along `heading` first so the cell ahead is always filled in.
+        let xs: Vec<usize> = if dx > 0 {
+            (0..width).rev().collect()
+        } else {
+            (0..width).collect()
+        };
+        let ys: Vec<usize> = if dy > 0 {
+            (0..height).rev().collect()
         } else {
-            break;
+            (0..height).collect()
+        };
+        for y in &ys {
+            for x in &xs {
+                let (ax, ay) = (*x as isize + dx, *y as isize + dy);
+                jumps[y * width + x] = match obstacles.get_isize(ax, ay) {
+                    None => None,
+                    Some(true) => Some(y * width + x),
+                    Some(false) => jumps[ay as usize * width + ax as usize],
+                };
+            }
         }
+        jumps
     }
-    return path;
-}
 
-fn has_cycle(world: &XYWorld<char>) -> bool {
-    let (x, y) = world.find_first(&'^').expect("start point not found");
-    let mut guard = Guard::new(x, y, 0_isize, -1_isize);
-    let mut path: Vec<Guard> = Vec::new();
-    loop {
-        let mut next_position = guard.clone();
-        if next_position.update().is_none() {
-            return false;
+    pub fn start(&self) -> Guard {
+        self.start
+    }
+
+    fn state(&self, guard: Guard) -> usize {
+        (guard.y * self.obstacles.width + guard.x) * 4 + guard.heading as usize
+    }
+
+    /// Where the guard stops walking from `guard`, taking an extra obstruction into account.
+    fn jump(&self, guard: Guard, obstruction: Option<(usize, usize)>) -> Option<(usize, usize)> {
+        let width = self.obstacles.width;
+        let stop = self.jumps[guard.heading as usize][guard.y * width + guard.x]
+            .map(|cell| (cell % width, cell / width));
+        let distance = |(x, y): (usize, usize)| x.abs_diff(guard.x) + y.abs_diff(guard.y);
+        let (dx, dy) = guard.heading.vector();
+        let blocking = obstruction.filter(|(ox, oy)| {
+            let (rx, ry) = (
+                *ox as isize - guard.x as isize,
+                *oy as isize - guard.y as isize,
+            );
+            // `obstruction` must be strictly ahead and no further than the real obstacle.
+            let ahead = rx * dy == ry * dx && rx * dx + ry * dy > 0;
+            ahead && stop.is_none_or(|stop| distance((*ox, *oy)) <= distance(stop) + 1)
+        });
+        match blocking {
+            Some((ox, oy)) => Some(((ox as isize - dx) as usize, (oy as isize - dy) as usize)),
+            None => stop,
         }
-        if path.contains(&next_position) {
-            break;
+    }
+
+    /// Follows the guard from `guard` one straight run at a time until it leaves or repeats a
+    /// state, with `obstruction` added to the map.
+    pub fn simulate(&self, guard: Guard, obstruction: Option<(usize, usize)>) -> Outcome {
+        let mut seen = StateSet::new(self.obstacles.width * self.obstacles.height * 4);
+        self.simulate_with(&mut seen, guard, obstruction)
+    }
+
+    fn simulate_with(
+        &self,
+        seen: &mut StateSet,
+        mut guard: Guard,
+        obstruction: Option<(usize, usize)>,
+    ) -> Outcome {
+        seen.clear();
+        while seen.insert(self.state(guard)) {
+            match self.jump(guard, obstruction) {
+                Some((x, y)) => {
+                    guard = Guard {
+                        x,
+                        y,
+                        heading: guard.heading.turn_right(),
+                    }
+                }
+                None => return Outcome::Exits,
+            }
         }
-        if let Some(peek_ch) = world.get(next_position.x, next_position.y) {
-            if *peek_ch == '#' {
-                guard.rotate();
-            } else {
-                path.push(next_position.clone());
-                guard = next_position;
+        let entry = guard;
+        let mut length = 0;
+        loop {
+            let (x, y) = self
+                .jump(guard, obstruction)
+                .expect("a looping guard can't leave");
+            length += x.abs_diff(guard.x) + y.abs_diff(guard.y);
+            guard = Guard {
+                x,
+                y,
+                heading: guard.heading.turn_right(),
+            };
+            if guard == entry {
+                return Outcome::Loops { length, entry };
             }
-        } else {
-            return false;
         }
     }
-    true
-}
 
-pub fn part_two(input: &str) -> Option<u32> {
-    let mut world = XYWorld::<char>::from_str(input);
-    let mut path = find_path(&world);
-    let start = world.find_first(&'^').expect("start point not found");
-    path.remove(&start);
-    let mut total = 0;
-    for (x, y) in path {
-        world.update_unsafe(x, y, '#');
-        if has_cycle(&world) {
-            total += 1;
+    /// Every distinct tile the guard walks onto after the start, paired with the guard as it was
+    /// just before stepping onto it for the first time.
+    pub fn route(&self) -> Vec<((usize, usize), Guard)> {
+        let mut visited = XYWorld::filled(self.obstacles.width, self.obstacles.height, false);
+        let mut seen = StateSet::new(self.obstacles.width * self.obstacles.height * 4);
+        let mut route = vec![];
+        let mut guard = self.start;
+        visited.update_unsafe(guard.x, guard.y, true);
+        while seen.insert(self.state(guard)) {
+            let (dx, dy) = guard.heading.vector();
+            let (nx, ny) = (guard.x as isize + dx, guard.y as isize + dy);
+            match self.obstacles.get_isize(nx, ny) {
+                None => break,
+                Some(true) => guard.heading = guard.heading.turn_right(),
+                Some(false) => {
+                    let next = (nx as usize, ny as usize);
+                    if !visited.get_unsafe(next.0, next.1) {
+                        visited.update_unsafe(next.0, next.1, true);
+                        route.push((next, guard));
+                    }
+                    (guard.x, guard.y) = next;
+                }
+            }
         }
-        world.update_unsafe(x, y, '.');
+        route
     }
-    Some(total)
+
+    /// Every tile where a single new obstruction traps the guard in a loop, with that loop.
+    pub fn loop_obstructions(&self) -> Vec<((usize, usize), Outcome)> {
+        let mut seen = StateSet::new(self.obstacles.width * self.obstacles.height * 4);
+        self.route()
+            .into_iter()
+            .filter_map(|(tile, before)| {
+                let outcome = self.simulate_with(&mut seen, before, Some(tile));
+                matches!(outcome, Outcome::Loops { .. }).then_some((tile, outcome))
+            })
+            .collect()
+    }
+}
+
+pub fn part_one(input: &str) -> Option<u32> {
+    let lab = Lab::parse(input).ok()?;
+    Some(lab.route().len() as u32 + 1)
+}
+
+pub fn part_two(input: &str) -> Option<u32> {
+    let lab = Lab::parse(input).ok()?;
+    Some(lab.loop_obstructions().len() as u32)
 }
 
 #[cfg(test)]
@@ -144,4 +274,47 @@ mod tests {
         let result = part_two(&advent_of_code::template::read_file("examples", DAY));
         assert_eq!(result, Some(6));
     }
+
+    #[test]
+    fn test_loop_outcomes() {
+        let lab = Lab::parse(&advent_of_code::template::read_file("examples", DAY)).unwrap();
+        assert_eq!(lab.simulate(lab.start(), None), Outcome::Exits);
+        let obstructions = lab.loop_obstructions();
+        assert!(obstructions.iter().any(|(tile, _)| *tile == (3, 6)));
+        // With a box at (3, 6) the guard circles the rectangle between (4, 1) and (8, 6).
+        let (_, outcome) = obstructions
+            .iter()
+            .find(|(tile, _)| *tile == (3, 6))
+            .unwrap();
+        assert_eq!(
+            *outcome,
+            Outcome::Loops {
+                length: 18,
+                entry: Guard {
+                    x: 4,
+                    y: 6,
+                    heading: Heading::Up
+                }
+            }
+        );
+
+        let small = ".#...\n....#\n.^...\n#....\n...#.";
+        let lab = Lab::parse(small).unwrap();
+        assert_eq!(
+            lab.simulate(lab.start(), None),
+            Outcome::Loops {
+                length: 8,
+                entry: Guard {
+                    x: 1,
+                    y: 1,
+                    heading: Heading::Right
+                }
+            }
+        );
+        assert_eq!(part_one(small), Some(8));
+        assert_eq!(
+            Lab::parse("..#\n...").err(),
+            Some(GridError::MissingMarker('^'))
+        );
+    }
 }
@@ -1,135 +1,294 @@
-use itertools::Itertools;
-use rayon::{iter::ParallelIterator, str::ParallelString};
+use advent_of_code::parse::{self, ParseError};
+use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
 
 advent_of_code::solution!(7);
 
-pub fn solutions(numbers: &Vec<usize>, filter: usize) -> Vec<usize> {
-    let mut answers: Vec<usize> = vec![];
-    let mut remaining = numbers.iter();
-    let first = remaining.next().unwrap();
-    let second = remaining.next().unwrap();
-    answers.push(first + second);
-    answers.push(first * second);
-    for number in remaining {
-        let mut new_answers: Vec<usize> = vec![];
-        while let Some(answer) = answers.pop() {
-            let addition = answer + number;
-            let multiply = answer * number;
-            if addition <= filter {
-                new_answers.push(addition);
-            }
-            if multiply <= filter {
-                new_answers.push(multiply);
-            }
+/// What an operator's inverse says about the left operand, given the result and right operand.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Inverse {
+    /// No left operand gives the result.
+    Impossible,
+    /// Only this left operand gives the result.
+    Left(u64),
+    /// Can't be worked out backwards, the solver falls back to evaluating the left side.
+    Unknown,
+}
+
+pub trait Operator: Send + Sync {
+    fn symbol(&self) -> &str;
+
+    /// `None` on overflow or when the operator isn't defined for the operands.
+    fn apply(&self, left: u64, right: u64) -> Option<u64>;
+
+    fn invert(&self, _result: u64, _right: u64) -> Inverse {
+        Inverse::Unknown
+    }
+}
+
+pub struct Add;
+
+impl Operator for Add {
+    fn symbol(&self) -> &str {
+        "+"
+    }
+
+    fn apply(&self, left: u64, right: u64) -> Option<u64> {
+        left.checked_add(right)
+    }
+
+    fn invert(&self, result: u64, right: u64) -> Inverse {
+        match result.checked_sub(right) {
+            Some(left) => Inverse::Left(left),
+            None => Inverse::Impossible,
         }
-        answers = new_answers;
     }
-    answers
 }
 
-pub fn part_one(input: &str) -> Option<usize> {
-    let answer = input
-        .par_lines()
-        .filter_map(|line| line.split_once(": "))
-        .map(|(test_str, numbers_str)| {
-            let test_value = test_str
-                .parse::<usize>()
-                .expect("could not parse test value");
-            let numbers = numbers_str
-                .split_ascii_whitespace()
-                .map(|number_str| number_str.parse::<usize>().expect("could not parse number"))
-                .collect_vec();
-            (test_value, numbers)
-        })
-        .map(|(test_value, numbers)| {
-            let solutions = solutions(&numbers, test_value);
-            if solutions.contains(&test_value) {
-                test_value as usize
-            } else {
-                0_usize
-            }
-        })
-        .sum::<usize>();
-    Some(answer)
+pub struct Mul;
+
+impl Operator for Mul {
+    fn symbol(&self) -> &str {
+        "*"
+    }
+
+    fn apply(&self, left: u64, right: u64) -> Option<u64> {
+        left.checked_mul(right)
+    }
+
+    fn invert(&self, result: u64, right: u64) -> Inverse {
+        match (result, right) {
+            (0, 0) => Inverse::Unknown,
+            (_, 0) => Inverse::Impossible,
+            (result, right) if result.is_multiple_of(right) => Inverse::Left(result / right),
+            _ => Inverse::Impossible,
+        }
+    }
 }
 
-pub fn solutions_three_operators(numbers: &Vec<usize>, filter: usize) -> Vec<usize> {
-    let mut answers: Vec<usize> = vec![];
-    let mut remaining = numbers.iter();
-    let first = remaining.next().unwrap();
-    let second = remaining.next().unwrap();
-    answers.push(first + second);
-    answers.push(first * second);
-    answers.push(format!("{}{}", first, second).parse::<usize>().unwrap());
-    for number in remaining {
-        let mut new_answers: Vec<usize> = vec![];
-        while let Some(answer) = answers.pop() {
-            if answer <= filter {
-                let addition = answer + number;
-                let multiply = answer * number;
-                let concat = format!("{}{}", answer, number).parse::<usize>().unwrap();
-                new_answers.push(addition);
-                new_answers.push(multiply);
-                new_answers.push(concat);
-            }
+/// Joins the digits of both operands, `12 || 345` is `12345`.
+pub struct Concat;
+
+impl Concat {
+    /// The power of ten that makes room for `right`'s digits, `None` once it no longer fits a `u64`.
+    fn shift(right: u64) -> Option<u64> {
+        10_u64.checked_pow(right.checked_ilog10().unwrap_or(0) + 1)
+    }
+}
+
+impl Operator for Concat {
+    fn symbol(&self) -> &str {
+        "||"
+    }
+
+    fn apply(&self, left: u64, right: u64) -> Option<u64> {
+        left.checked_mul(Concat::shift(right)?)?.checked_add(right)
+    }
+
+    fn invert(&self, result: u64, right: u64) -> Inverse {
+        let Some(shift) = Concat::shift(right) else {
+            return Inverse::Impossible;
+        };
+        if result % shift == right {
+            Inverse::Left(result / shift)
+        } else {
+            Inverse::Impossible
         }
-        answers = new_answers;
     }
-    answers
 }
 
-pub fn part_two(input: &str) -> Option<usize> {
-    let answer = input
-        .par_lines()
-        .filter_map(|line| line.split_once(": "))
-        .map(|(test_str, numbers_str)| {
-            let test_value = test_str
-                .parse::<usize>()
-                .expect("could not parse test value");
-            let numbers = numbers_str
-                .split_ascii_whitespace()
-                .map(|number_str| number_str.parse::<usize>().expect("could not parse number"))
-                .collect_vec();
-            (test_value, numbers)
-        })
-        .map(|(test_value, numbers)| {
-            let solutions = solutions_three_operators(&numbers, test_value);
-            if solutions.contains(&test_value) {
-                test_value as usize
-            } else {
-                0_usize
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Equation {
+    pub target: u64,
+    pub numbers: Vec<u64>,
+}
+
+impl Equation {
+    pub fn parse(input: &str) -> Result<Vec<Equation>, ParseError> {
+        parse::lines(input)
+            .map(|line| {
+                let (target, numbers) = line.pair::<u64, String>(":")?;
+                let numbers = numbers
+                    .split_ascii_whitespace()
+                    .map(|number| {
+                        number
+                            .parse()
+                            .map_err(|_| line.error(format!("could not parse {number:?}")))
+                    })
+                    .collect::<Result<Vec<u64>, _>>()?;
+                if numbers.is_empty() {
+                    return Err(line.error("no numbers"));
+                }
+                Ok(Equation { target, numbers })
+            })
+            .collect()
+    }
+}
+
+/// Decides which operators, evaluated left to right, turn a list of numbers into a target. The
+/// search runs right to left, undoing the last operator with its inverse to prune early.
+pub struct Solver {
+    operators: Vec<Box<dyn Operator>>,
+}
+
+impl Solver {
+    pub fn new(operators: Vec<Box<dyn Operator>>) -> Solver {
+        Solver { operators }
+    }
+
+    pub fn add_mul() -> Solver {
+        Solver::new(vec![Box::new(Add), Box::new(Mul)])
+    }
+
+    pub fn add_mul_concat() -> Solver {
+        Solver::new(vec![Box::new(Add), Box::new(Mul), Box::new(Concat)])
+    }
+
+    /// Calls `found` with the operator indices of each solution, stopping once it returns true.
+    fn search<F>(&self, target: u64, numbers: &[u64], ops: &mut [usize], found: &mut F) -> bool
+    where
+        F: FnMut(&[usize]) -> bool,
+    {
+        let Some((right, rest)) = numbers.split_last() else {
+            return false;
+        };
+        if rest.is_empty() {
+            return *right == target && found(ops);
+        }
+        for (index, operator) in self.operators.iter().enumerate() {
+            ops[rest.len() - 1] = index;
+            let stop = match operator.invert(target, *right) {
+                Inverse::Impossible => false,
+                Inverse::Left(left) => self.search(left, rest, ops, found),
+                Inverse::Unknown => self.evaluate(rest, 1, rest[0], ops, &mut |left, ops| {
+                    operator.apply(left, *right) == Some(target) && found(ops)
+                }),
+            };
+            if stop {
+                return true;
+            }
+        }
+        false
+    }
+
+    /// Every value of `numbers[..]` from `index` on, starting from `value`, filling in `ops`.
+    fn evaluate<F>(
+        &self,
+        numbers: &[u64],
+        index: usize,
+        value: u64,
+        ops: &mut [usize],
+        f: &mut F,
+    ) -> bool
+    where
+        F: FnMut(u64, &[usize]) -> bool,
+    {
+        if index == numbers.len() {
+            return f(value, ops);
+        }
+        for (op, operator) in self.operators.iter().enumerate() {
+            if let Some(next) = operator.apply(value, numbers[index]) {
+                ops[index - 1] = op;
+                if self.evaluate(numbers, index + 1, next, ops, f) {
+                    return true;
+                }
             }
-        })
-        .sum::<usize>();
-    Some(answer)
+        }
+        false
+    }
+
+    /// One solution written out, like `81 + 40 * 27`.
+    pub fn witness(&self, equation: &Equation) -> Option<String> {
+        let mut ops = vec![0; equation.numbers.len().saturating_sub(1)];
+        let mut witness = None;
+        self.search(equation.target, &equation.numbers, &mut ops, &mut |ops| {
+            let mut expression = equation.numbers[0].to_string();
+            for (op, number) in ops.iter().zip(&equation.numbers[1..]) {
+                expression.push_str(&format!(" {} {}", self.operators[*op].symbol(), number));
+            }
+            witness = Some(expression);
+            true
+        });
+        witness
+    }
+
+    pub fn is_solvable(&self, equation: &Equation) -> bool {
+        let mut ops = vec![0; equation.numbers.len().saturating_sub(1)];
+        self.search(equation.target, &equation.numbers, &mut ops, &mut |_| true)
+    }
+
+    /// Number of operator assignments that reach the target.
+    pub fn count(&self, equation: &Equation) -> u64 {
+        let mut ops = vec![0; equation.numbers.len().saturating_sub(1)];
+        let mut count = 0;
+        self.search(equation.target, &equation.numbers, &mut ops, &mut |_| {
+            count += 1;
+            false
+        });
+        count
+    }
+
+    pub fn calibration(&self, equations: &[Equation]) -> u64 {
+        equations
+            .par_iter()
+            .filter(|equation| self.is_solvable(equation))
+            .map(|equation| equation.target)
+            .sum()
+    }
+}
+
+pub fn part_one(input: &str) -> Option<u64> {
+    let equations = Equation::parse(input).ok()?;
+    Some(Solver::add_mul().calibration(&equations))
+}
+
+pub fn part_two(input: &str) -> Option<u64> {
+    let equations = Equation::parse(input).ok()?;
+    Some(Solver::add_mul_concat().calibration(&equations))
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    fn equation(target: u64, numbers: &[u64]) -> Equation {
+        Equation {
+            target,
+            numbers: numbers.to_vec(),
+        }
+    }
+
     #[test]
     fn two_numbers_third_op() {
-        let result = solutions_three_operators(&vec![10, 19], 1019);
-        assert_eq!(result, vec![29, 190, 1019]);
+        let solver = Solver::add_mul_concat();
+        assert_eq!(
+            solver.witness(&equation(1019, &[10, 19])).as_deref(),
+            Some("10 || 19")
+        );
+        assert!(!Solver::add_mul().is_solvable(&equation(1019, &[10, 19])));
     }
 
     #[test]
     fn two_numbers() {
-        let result = solutions(&vec![10, 19], 190);
-        assert_eq!(result, vec![29, 190]);
+        let solver = Solver::add_mul();
+        assert_eq!(
+            solver.witness(&equation(190, &[10, 19])).as_deref(),
+            Some("10 * 19")
+        );
+        assert!(solver.is_solvable(&equation(29, &[10, 19])));
     }
 
     #[test]
     fn three_numbers() {
-        let result = solutions(&vec![81, 40, 27], 87480);
-        assert_eq!(result, vec![3267, 87480, 148, 3267]);
+        let solver = Solver::add_mul();
+        assert!(solver.is_solvable(&equation(87480, &[81, 40, 27])));
+        assert!(solver.is_solvable(&equation(148, &[81, 40, 27])));
     }
 
     #[test]
     fn three_numbers_with_filter() {
-        let result = solutions(&vec![81, 40, 27], 3267);
-        assert_eq!(result, vec![3267, 148, 3267]);
+        let solver = Solver::add_mul();
+        assert_eq!(solver.count(&equation(3267, &[81, 40, 27])), 2);
+        assert!(!solver.is_solvable(&equation(3268, &[81, 40, 27])));
     }
 
     #[test]
@@ -143,4 +302,40 @@ mod tests {
         let result = part_two(&advent_of_code::template::read_file("examples", DAY));
         assert_eq!(result, Some(11387));
     }
+
+    struct Sub;
+
+    impl Operator for Sub {
+        fn symbol(&self) -> &str {
+            "-"
+        }
+
+        fn apply(&self, left: u64, right: u64) -> Option<u64> {
+            left.checked_sub(right)
+        }
+    }
+
+    #[test]
+    fn test_user_defined_operators() {
+        let solver = Solver::new(vec![Box::new(Add), Box::new(Sub)]);
+        assert_eq!(
+            solver.witness(&equation(4, &[10, 3, 3])).as_deref(),
+            Some("10 - 3 - 3")
+        );
+        assert_eq!(solver.count(&equation(10, &[10, 3, 3])), 2);
+        // Multiplying by zero can't be inverted, so the left side is evaluated instead.
+        let solver = Solver::add_mul();
+        assert_eq!(solver.count(&equation(0, &[5, 2, 0])), 2);
+        assert_eq!(solver.count(&equation(0, &[0, 0, 0])), 4);
+    }
+
+    #[test]
+    fn test_concat_overflow() {
+        let big = 10_u64.pow(19);
+        assert_eq!(Concat.apply(1, big), None);
+        assert_eq!(Concat.invert(big, big), Inverse::Impossible);
+        let solver = Solver::add_mul_concat();
+        assert!(!solver.is_solvable(&equation(7, &[1, big])));
+        assert!(solver.is_solvable(&equation(big + 1, &[1, big])));
+    }
 }
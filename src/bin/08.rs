@@ -1,78 +1,134 @@
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, HashSet};
 
-use advent_of_code::XYWorld;
+use advent_of_code::{math::gcd, XYWorld};
 use itertools::Itertools;
 
 advent_of_code::solution!(8);
 
-pub fn part_one(input: &str) -> Option<usize> {
-    let world: XYWorld<char> = XYWorld::<char>::from_str(input);
-    let mut points: HashSet<(usize, usize)> = HashSet::new();
-    let mut antennas: HashMap<char, Vec<(usize, usize)>> = HashMap::new();
-    for x in 0..world.width {
+/// Which points on the line through a pair of antennas count as antinodes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Harmonics {
+    /// Only points twice as far from one antenna as the other, outside the pair.
+    Double,
+    /// Every grid point on the line.
+    All,
+    /// Points `k` times the pair's separation from one antenna, on the far side of the other. Only
+    /// `k >= 2` lies past the pair, smaller `k` lands on the antennas and yields no antinodes.
+    Multiple(u32),
+}
+
+pub struct Antennas {
+    width: usize,
+    height: usize,
+    frequencies: BTreeMap<char, Vec<(isize, isize)>>,
+}
+
+impl Antennas {
+    /// Any character other than `.` or `#` is an antenna of that frequency.
+    pub fn new(world: &XYWorld<char>) -> Antennas {
+        let mut frequencies: BTreeMap<char, Vec<(isize, isize)>> = BTreeMap::new();
         for y in 0..world.height {
-            let ch = world.get(x, y).unwrap();
-            if *ch != '.' {
-                let coords = antennas.entry(*ch).or_insert(vec![]);
-                coords.push((x, y));
+            for x in 0..world.width {
+                let ch = world.get_unsafe(x, y);
+                if *ch != '.' && *ch != '#' {
+                    frequencies
+                        .entry(*ch)
+                        .or_default()
+                        .push((x as isize, y as isize));
+                }
             }
         }
+        Antennas {
+            width: world.width,
+            height: world.height,
+            frequencies,
+        }
+    }
+
+    pub fn frequencies(&self) -> impl Iterator<Item = (char, &[(isize, isize)])> {
+        self.frequencies
+            .iter()
+            .map(|(frequency, antennas)| (*frequency, antennas.as_slice()))
     }
-    for coords in antennas.values() {
-        for ((fx, fy), (sx, sy)) in coords.iter().tuple_combinations::<(_, _)>() {
-            let fx_isize = *fx as isize;
-            let fy_isize = *fy as isize;
-            let sx_isize = *sx as isize;
-            let sy_isize = *sy as isize;
-            let fdx = fx_isize - sx_isize;
-            let fdy = fy_isize - sy_isize;
-            let sdx = sx_isize - fx_isize;
-            let sdy = sy_isize - fy_isize;
-            if world.contains(fx_isize + fdx, fy_isize + fdy) {
-                points.insert(((fx_isize + fdx) as usize, (fy_isize + fdy) as usize));
+
+    fn contains(&self, x: isize, y: isize) -> bool {
+        x >= 0 && y >= 0 && x < self.width as isize && y < self.height as isize
+    }
+
+    /// Antinodes on the map for one pair of antennas.
+    fn pair_antinodes(
+        &self,
+        (ax, ay): (isize, isize),
+        (bx, by): (isize, isize),
+        harmonics: Harmonics,
+    ) -> Vec<(isize, isize)> {
+        let (dx, dy) = (bx - ax, by - ay);
+        match harmonics {
+            Harmonics::Double => self.pair_antinodes((ax, ay), (bx, by), Harmonics::Multiple(2)),
+            Harmonics::Multiple(k) => {
+                let Some(k) = isize::try_from(k).ok().filter(|k| *k >= 2) else {
+                    return vec![];
+                };
+                let (Some(ox), Some(oy)) = (dx.checked_mul(k), dy.checked_mul(k)) else {
+                    return vec![];
+                };
+                let beyond_b = ax.checked_add(ox).zip(ay.checked_add(oy));
+                let beyond_a = bx.checked_sub(ox).zip(by.checked_sub(oy));
+                [beyond_b, beyond_a]
+                    .into_iter()
+                    .flatten()
+                    .filter(|(x, y)| self.contains(*x, *y))
+                    .collect()
             }
-            if world.contains(sx_isize + sdx, sy_isize + sdy) {
-                points.insert(((sx_isize + sdx) as usize, (sy_isize + sdy) as usize));
+            Harmonics::All => {
+                // Reduce the step so points between lattice multiples of the pair are found too.
+                let divisor = gcd(dx as i64, dy as i64).max(1) as isize;
+                let (sx, sy) = (dx / divisor, dy / divisor);
+                let mut points = vec![];
+                for (sx, sy) in [(sx, sy), (-sx, -sy)] {
+                    let (mut x, mut y) = (ax, ay);
+                    while self.contains(x, y) {
+                        points.push((x, y));
+                        x += sx;
+                        y += sy;
+                    }
+                }
+                points.into_iter().unique().collect()
             }
         }
     }
-    Some(points.len())
-}
 
-pub fn part_two(input: &str) -> Option<usize> {
-    let world = XYWorld::<char>::from_str(input);
-    let mut points: HashSet<(usize, usize)> = HashSet::new();
-    let mut antennas: HashMap<char, Vec<(usize, usize)>> = HashMap::new();
-    for x in 0..world.width {
-        for y in 0..world.height {
-            let ch = world.get(x, y).unwrap();
-            if *ch != '.' {
-                let coords = antennas.entry(*ch).or_insert(vec![]);
-                coords.push((x, y));
-            }
-        }
+    /// Every position that is an antinode for at least one pair of antennas.
+    pub fn antinodes(&self, harmonics: Harmonics) -> HashSet<(isize, isize)> {
+        self.frequencies()
+            .flat_map(|(_, antennas)| antennas.iter().tuple_combinations())
+            .flat_map(|(a, b)| self.pair_antinodes(*a, *b, harmonics))
+            .collect()
     }
-    for coords in antennas.values() {
-        for ((fx, fy), (sx, sy)) in coords.iter().tuple_combinations::<(_, _)>() {
-            let (mut fx_isize, mut fy_isize) = (*fx as isize, *fy as isize);
-            let (mut sx_isize, mut sy_isize) = (*sx as isize, *sy as isize);
-            let fdx = fx_isize - sx_isize;
-            let fdy = fy_isize - sy_isize;
-            let sdx = sx_isize - fx_isize;
-            let sdy = sy_isize - fy_isize;
-            while world.contains(fx_isize, fy_isize) {
-                points.insert((fx_isize as usize, fy_isize as usize));
-                fx_isize += fdx;
-                fy_isize += fdy;
-            }
-            while world.contains(sx_isize, sy_isize) {
-                points.insert((sx_isize as usize, sy_isize as usize));
-                sx_isize += sdx;
-                sy_isize += sdy;
+
+    /// The map with antinodes drawn as `#` wherever there isn't an antenna.
+    pub fn overlay(&self, harmonics: Harmonics) -> XYWorld<char> {
+        let mut world = XYWorld::<char>::blank(self.width, self.height);
+        for (x, y) in self.antinodes(harmonics) {
+            world.update_unsafe(x as usize, y as usize, '#');
+        }
+        for (frequency, antennas) in self.frequencies() {
+            for (x, y) in antennas {
+                world.update_unsafe(*x as usize, *y as usize, frequency);
             }
         }
+        world
     }
-    Some(points.len())
+}
+
+pub fn part_one(input: &str) -> Option<usize> {
+    let antennas = Antennas::new(&XYWorld::<char>::parse(input).ok()?);
+    Some(antennas.antinodes(Harmonics::Double).len())
+}
+
+pub fn part_two(input: &str) -> Option<usize> {
+    let antennas = Antennas::new(&XYWorld::<char>::parse(input).ok()?);
+    Some(antennas.antinodes(Harmonics::All).len())
 }
 
 #[cfg(test)]
@@ -90,4 +146,34 @@ mod tests {
         let result = part_two(&advent_of_code::template::read_file("examples", DAY));
         assert_eq!(result, Some(34));
     }
+
+    #[test]
+    fn test_harmonics() {
+        let world = XYWorld::<char>::parse("..........\n...#......\n#.........\n....a.....\n........a.\n.....a....\n..#.......\n......#...\n..........\n..........").unwrap();
+        let antennas = Antennas::new(&world);
+        assert_eq!(antennas.frequencies().count(), 1);
+        assert_eq!(
+            antennas.overlay(Harmonics::Double).to_string(),
+            world.to_string()
+        );
+
+        let world = XYWorld::<char>::parse("A.....\n......\n....A.\n......\n......").unwrap();
+        let antennas = Antennas::new(&world);
+        // The pair is 4 across and 2 down, so the gcd-reduced line also passes through (2, 1).
+        assert_eq!(
+            antennas.overlay(Harmonics::All).to_string(),
+            "A.....\n..#...\n....A.\n......\n......"
+        );
+        assert_eq!(antennas.antinodes(Harmonics::All).len(), 3);
+        assert_eq!(antennas.antinodes(Harmonics::Multiple(2)).len(), 0);
+
+        let antennas = Antennas::new(&XYWorld::<char>::parse("a.a......").unwrap());
+        assert_eq!(
+            antennas.antinodes(Harmonics::Multiple(3)),
+            HashSet::from([(6, 0)])
+        );
+        assert!(antennas.antinodes(Harmonics::Multiple(1)).is_empty());
+        assert!(antennas.antinodes(Harmonics::Multiple(0)).is_empty());
+        assert!(antennas.antinodes(Harmonics::Multiple(u32::MAX)).is_empty());
+    }
 }
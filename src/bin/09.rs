@@ -1,172 +1,202 @@
-#![feature(iter_array_chunks)]
+use std::{
+    cmp::Reverse,
+    collections::BinaryHeap,
+    fmt::{Display, Write},
+};
 
-use std::collections::VecDeque;
+use advent_of_code::parse::{self, ParseError};
 
 advent_of_code::solution!(9);
 
-#[derive(Debug)]
-struct FileBlock {
-    id: usize,
-    size: usize,
+/// A run of blocks belonging to one file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Extent {
+    pub id: usize,
+    pub start: usize,
+    pub len: usize,
 }
 
-impl FileBlock {
-    fn new(id: usize, size: usize) -> FileBlock {
-        FileBlock { id, size }
-    }
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DiskMap {
+    /// Sorted by `start`, a file split by block compaction has one extent per piece.
+    extents: Vec<Extent>,
+    len: usize,
 }
 
-pub fn part_one(input: &str) -> Option<usize> {
-    let mut numbers = input
-        .chars()
-        .filter_map(|num| num.to_string().parse::<usize>().ok())
-        .enumerate()
-        .collect::<VecDeque<_>>();
-    let mut disk: Vec<FileBlock> = Vec::new();
-    while let Some((index, mut number)) = numbers.pop_front() {
-        if index % 2 == 1 {
-            while number > 0 {
-                if let Some((index_o, other)) = numbers.pop_back() {
-                    if index_o % 2 == 0 {
-                        let id_o = index_o / 2;
-                        if other <= number {
-                            number -= other;
-                            disk.push(FileBlock::new(id_o, other));
-                        } else {
-                            disk.push(FileBlock::new(id_o, number));
-                            let remaining = other - number;
-                            number = 0;
-                            numbers.push_back((index_o, remaining));
-                        }
-                    }
+impl DiskMap {
+    /// Parses the dense format, alternating digits for file and free space lengths. File ids count
+    /// every file digit, including empty files, across all lines.
+    pub fn parse(input: &str) -> Result<DiskMap, ParseError> {
+        let mut extents = vec![];
+        let mut len = 0;
+        let mut index = 0;
+        for line in parse::lines(input) {
+            for (column, ch) in line.text.chars().enumerate() {
+                let size = ch.to_digit(10).ok_or_else(|| {
+                    line.error(format!("invalid digit {ch:?} at column {}", column + 1))
+                })? as usize;
+                if index % 2 == 0 && size > 0 {
+                    extents.push(Extent {
+                        id: index / 2,
+                        start: len,
+                        len: size,
+                    });
                 }
+                len += size;
+                index += 1;
             }
-        } else {
-            let id = index / 2;
-            disk.push(FileBlock::new(id, number));
-        }
-    }
-    let mut idx = 0;
-    let mut result = 0;
-    for block in disk {
-        for _ in 0..block.size {
-            result += block.id * idx;
-            idx += 1;
         }
+        Ok(DiskMap { extents, len })
     }
 
-    Some(result)
-}
-
-#[derive(Debug, PartialEq, Eq)]
-pub enum Block {
-    FileBlock { id: usize, size: usize },
-    FreeBlock { size: usize },
-}
-
-impl Block {
-    pub fn free(size: usize) -> Block {
-        Block::FreeBlock { size }
+    pub fn extents(&self) -> &[Extent] {
+        &self.extents
     }
 
-    pub fn file(id: usize, size: usize) -> Block {
-        Block::FileBlock { id, size }
+    /// Every block in order, `None` for free space.
+    pub fn blocks(&self) -> Vec<Option<usize>> {
+        let mut blocks = vec![None; self.len];
+        for extent in &self.extents {
+            blocks[extent.start..extent.start + extent.len].fill(Some(extent.id));
+        }
+        blocks
     }
-}
 
-pub fn merge_free(mut disk: Vec<Block>) -> Vec<Block> {
-    let mut compacted_disk: Vec<Block> = Vec::new();
-    let mut compacting: usize = 0;
-    while let Some(block) = disk.pop() {
-        match block {
-            Block::FileBlock { id: _, size: _ } => {
-                if compacting > 0 {
-                    compacted_disk.push(Block::free(compacting));
-                    compacting = 0;
-                }
-                compacted_disk.push(block);
+    /// Moves single blocks from the end of the disk into the left-most free block until there are
+    /// no gaps, splitting files as needed.
+    pub fn compact_blocks(&mut self) {
+        let mut blocks = self.blocks();
+        let (mut free, mut last) = (0, blocks.len());
+        loop {
+            while free < last && blocks[free].is_some() {
+                free += 1;
+            }
+            while last > free && blocks[last - 1].is_none() {
+                last -= 1;
+            }
+            if last <= free + 1 {
+                break;
             }
-            Block::FreeBlock { size } => compacting += size,
+            blocks.swap(free, last - 1);
         }
+        self.extents = blocks
+            .chunk_by(|a, b| a == b)
+            .scan(0, |start, run| {
+                let extent = run[0].map(|id| Extent {
+                    id,
+                    start: *start,
+                    len: run.len(),
+                });
+                *start += run.len();
+                Some(extent)
+            })
+            .flatten()
+            .collect();
     }
-    compacted_disk.reverse();
-    compacted_disk
-}
 
-pub fn part_two(input: &str) -> Option<usize> {
-    let numbers = input
-        .chars()
-        .filter_map(|num| num.to_string().parse::<usize>().ok())
-        .enumerate();
-    let mut disk: Vec<Block> = Vec::new();
-    for (index, amount) in numbers {
-        if index % 2 == 1 {
-            if amount > 0 {
-                disk.push(Block::free(amount));
+    /// The free space between extents as `(start, len)`, including any space before the first.
+    fn gaps(&self) -> Vec<(usize, usize)> {
+        let mut gaps = vec![];
+        let mut end = 0;
+        for extent in &self.extents {
+            if extent.start > end {
+                gaps.push((end, extent.start - end));
             }
-        } else {
-            let id = index / 2;
-            disk.push(Block::file(id, amount));
+            end = extent.start + extent.len;
         }
+        gaps
     }
-    let mut pos = disk.len();
-    while pos != 0 {
-        pos -= 1;
-        if let Some(Block::FileBlock { id: _, size }) = disk.get(pos) {
-            let mut swap_pos: Option<usize> = None;
-            let mut remaining_opt: Option<usize> = None;
-            for (other_pos, other) in disk[0..pos].iter().enumerate() {
-                match other {
-                    Block::FileBlock { id: _, size: _ } => (),
-                    Block::FreeBlock { size: free } => {
-                        if size == free {
-                            swap_pos = Some(other_pos);
-                            break;
-                        } else if size < free {
-                            remaining_opt = Some(free - size);
-                            swap_pos = Some(other_pos);
-                            break;
-                        }
-                    }
-                }
-            }
-            if let Some(other_pos) = swap_pos {
-                disk.swap(pos, other_pos);
-                if let Some(remaining) = remaining_opt {
-                    if let Some(free_block) = disk.get_mut(pos) {
-                        match free_block {
-                            Block::FileBlock { size: _, id: _ } => {
-                                panic!("should not be a file block")
-                            }
-                            Block::FreeBlock { size } => {
-                                *free_block = Block::free(*size - remaining)
-                            }
-                        };
-                    }
-                    disk.insert(other_pos + 1, Block::FreeBlock { size: remaining });
-                    pos += 1;
-                }
+
+    /// Moves each file whole, highest id first, into the left-most gap that fits it, if one is left
+    /// of the file. Gap starts are kept in a min-heap per gap size.
+    pub fn compact_files(&mut self) {
+        let gaps = self.gaps();
+        let largest = gaps.iter().map(|(_, len)| *len).max().unwrap_or(0);
+        let mut heaps: Vec<BinaryHeap<Reverse<usize>>> = vec![BinaryHeap::new(); largest + 1];
+        for (start, len) in gaps {
+            heaps[len].push(Reverse(start));
+        }
+        let mut order: Vec<usize> = (0..self.extents.len()).collect();
+        order.sort_by_key(|index| Reverse((self.extents[*index].id, self.extents[*index].start)));
+        for index in order {
+            let extent = self.extents[index];
+            let best = (extent.len..heaps.len())
+                .filter_map(|size| heaps[size].peek().map(|Reverse(start)| (*start, size)))
+                .min()
+                .filter(|(start, _)| *start < extent.start);
+            if let Some((start, size)) = best {
+                heaps[size].pop();
+                heaps[size - extent.len].push(Reverse(start + extent.len));
+                self.extents[index].start = start;
             }
         }
+        self.extents.sort_by_key(|extent| extent.start);
     }
-    let disk = merge_free(disk);
-    let mut idx = 0;
-    let mut result = 0;
-    for block in disk {
-        match block {
-            Block::FileBlock { id, size } => {
-                for _ in 0..size {
-                    result += id * idx;
-                    idx += 1;
-                }
+
+    pub fn checksum(&self) -> usize {
+        self.extents
+            .iter()
+            .map(|extent| extent.id * (extent.start..extent.start + extent.len).sum::<usize>())
+            .sum()
+    }
+
+    /// The dense format for this layout, only possible while the files are whole, in id order and
+    /// every run fits in one digit. Empty files are placed straight after the file before them.
+    pub fn to_dense(&self) -> Option<String> {
+        let mut runs: Vec<(usize, usize)> = vec![];
+        let mut end = 0;
+        for extent in &self.extents {
+            if extent.id < runs.len() {
+                return None;
+            }
+            while runs.len() < extent.id {
+                runs.push((end, 0));
             }
-            Block::FreeBlock { size } => {
-                idx += size;
+            runs.push((extent.start, extent.len));
+            end = extent.start + extent.len;
+        }
+        let mut dense = String::new();
+        let mut end = 0;
+        for (start, len) in runs {
+            if !dense.is_empty() {
+                dense.push(char::from_digit((start - end) as u32, 10)?);
+            } else if start > 0 {
+                return None;
             }
+            dense.push(char::from_digit(len as u32, 10)?);
+            end = start + len;
         }
+        if end < self.len {
+            dense.push(char::from_digit((self.len - end) as u32, 10)?);
+        }
+        Some(dense)
     }
+}
 
-    Some(result)
+/// Renders the `00...111...2` layout, ids above 9 continue as letters and ids past 35 as `#`.
+impl Display for DiskMap {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for block in self.blocks() {
+            let ch = match block {
+                Some(id) => char::from_digit(id as u32, 36).unwrap_or('#'),
+                None => '.',
+            };
+            f.write_char(ch)?;
+        }
+        Ok(())
+    }
+}
+
+pub fn part_one(input: &str) -> Option<usize> {
+    let mut disk = DiskMap::parse(input).ok()?;
+    disk.compact_blocks();
+    Some(disk.checksum())
+}
+
+pub fn part_two(input: &str) -> Option<usize> {
+    let mut disk = DiskMap::parse(input).ok()?;
+    disk.compact_files();
+    Some(disk.checksum())
 }
 
 #[cfg(test)]
@@ -180,22 +210,50 @@ mod tests {
     }
 
     #[test]
-    fn test_merge_frees() {
-        let result = merge_free(vec![
-            Block::file(0, 1),
-            Block::free(1),
-            Block::free(1),
-            Block::file(1, 1),
-        ]);
+    fn test_part_two() {
+        let result = part_two(&advent_of_code::template::read_file("examples", DAY));
+        assert_eq!(result, Some(2858));
+    }
+
+    #[test]
+    fn test_disk_map_layouts() {
+        let input = advent_of_code::template::read_file("examples", DAY);
+        let disk = DiskMap::parse(&input).unwrap();
         assert_eq!(
-            result,
-            vec![Block::file(0, 1), Block::free(2), Block::file(1, 1)]
+            disk.to_string(),
+            "00...111...2...333.44.5555.6666.777.888899"
         );
+        assert_eq!(disk.to_dense().as_deref(), Some(input.trim()));
+
+        let mut blocks = disk.clone();
+        blocks.compact_blocks();
+        assert_eq!(
+            blocks.to_string(),
+            "0099811188827773336446555566.............."
+        );
+        assert_eq!(blocks.to_dense(), None);
+
+        let mut files = disk.clone();
+        files.compact_files();
+        assert_eq!(
+            files.to_string(),
+            "00992111777.44.333....5555.6666.....8888.."
+        );
+
+        let small = DiskMap::parse("12345").unwrap();
+        assert_eq!(small.to_string(), "0..111....22222");
+        assert!(DiskMap::parse("12a").is_err());
     }
 
     #[test]
-    fn test_part_two() {
-        let result = part_two(&advent_of_code::template::read_file("examples", DAY));
-        assert_eq!(result, Some(2858));
+    fn test_empty_files() {
+        // File 1 is empty, so file 2 keeps its id.
+        let disk = DiskMap::parse("12031").unwrap();
+        assert_eq!(disk.to_string(), "0.....2");
+        assert_eq!(part_one("12031"), Some(2));
+        assert_eq!(disk.to_dense().as_deref(), Some("10051"));
+        assert_eq!(DiskMap::parse("10051").unwrap().blocks(), disk.blocks());
+        // Parity carries over between lines.
+        assert_eq!(DiskMap::parse("120\n31").unwrap(), disk);
     }
 }
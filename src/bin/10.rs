@@ -1,141 +1,169 @@
-use std::collections::{HashMap, HashSet};
-
-use advent_of_code::XYWorld;
+use advent_of_code::{grid::GridError, XYWorld};
 
 advent_of_code::solution!(10);
 
 const DIRS: [(isize, isize); 4] = [(-1, 0), (0, 1), (1, 0), (0, -1)];
 
-#[derive(Debug)]
-struct Agent {
-    x: usize,
-    y: usize,
-    dx: isize,
-    dy: isize,
-    path: Vec<(usize, usize)>,
+/// A height map viewed as a DAG, with an edge wherever a neighbour is exactly `step` higher.
+pub struct TopoMap {
+    heights: XYWorld<usize>,
+    step: usize,
+}
+
+/// Trails from every cell of `start` height up to cells of `target` height.
+pub struct Trails {
+    width: usize,
+    trailheads: Vec<(usize, usize)>,
+    summits: Vec<(usize, usize)>,
+    /// One bitset over `summits` per cell, `words` u64s each.
+    reachable: Vec<u64>,
+    words: usize,
+    paths: Vec<u64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TopoError {
+    Grid(GridError),
+    /// A zero step height allows flat moves, which would make cycles.
+    ZeroStep,
 }
 
-impl Agent {
-    fn new(x: usize, y: usize, dx: isize, dy: isize) -> Agent {
-        Agent {
-            x,
-            y,
-            dx,
-            dy,
-            path: vec![],
+impl From<GridError> for TopoError {
+    fn from(error: GridError) -> Self {
+        TopoError::Grid(error)
+    }
+}
+
+impl TopoMap {
+    /// Panics if `step` is zero, since flat moves would allow cycles. `TopoMap::parse` reports it
+    /// as an error instead.
+    pub fn new(heights: XYWorld<usize>, step: usize) -> TopoMap {
+        assert!(step > 0, "a zero step height doesn't give a DAG");
+        TopoMap { heights, step }
+    }
+
+    pub fn parse(input: &str, step: usize) -> Result<TopoMap, TopoError> {
+        if step == 0 {
+            return Err(TopoError::ZeroStep);
         }
+        Ok(TopoMap::new(XYWorld::parse(input)?, step))
     }
 
-    fn with_path(x: usize, y: usize, dx: isize, dy: isize, path: Vec<(usize, usize)>) -> Agent {
-        Agent { x, y, dx, dy, path }
+    /// Neighbours exactly one step higher than `(x, y)`.
+    pub fn uphill(&self, x: usize, y: usize) -> impl Iterator<Item = (usize, usize)> + '_ {
+        let height = *self.heights.get_unsafe(x, y);
+        DIRS.iter().filter_map(move |(dx, dy)| {
+            let (nx, ny) = (x as isize + dx, y as isize + dy);
+            let next = self.heights.get_isize(nx, ny)?;
+            (*next == height + self.step).then_some((nx as usize, ny as usize))
+        })
     }
-}
 
-pub fn part_one(input: &str) -> Option<usize> {
-    let world: XYWorld<usize> = XYWorld::<usize>::from_str(input);
-    let mut paths: HashSet<Vec<(usize, usize)>> = HashSet::new();
-    for x in 0..world.width {
-        for y in 0..world.height {
-            let height = world.get_unsafe(x, y);
-            if *height == 0 {
-                let mut visited: HashSet<(usize, usize)> = HashSet::new();
-                let mut agents: Vec<Agent> = vec![];
-                for (dx, dy) in DIRS {
-                    let mut agent = Agent::new(x, y, dx, dy);
-                    agent.path.push((x, y));
-                    agents.push(agent);
-                }
-                while let Some(mut agent) = agents.pop() {
-                    let Agent {
-                        x,
-                        y,
-                        dx,
-                        dy,
-                        ref mut path,
-                    } = agent;
-                    if let (Some(nx), Some(ny)) =
-                        (x.checked_add_signed(dx), y.checked_add_signed(dy))
-                    {
-                        if world.contains(nx as isize, ny as isize) && !visited.contains(&(nx, ny))
-                        {
-                            let current = world.get_unsafe(x, y);
-                            let height = world.get_unsafe(nx, ny);
-                            if height.checked_sub(*current).is_some_and(|diff| diff == 1) {
-                                path.push((nx, ny));
-                                visited.insert((nx, ny));
-                                if path.len() == 10 {
-                                    paths.insert(path.to_vec());
-                                } else {
-                                    for (dx, dy) in DIRS {
-                                        let agent = Agent::with_path(nx, ny, dx, dy, path.clone());
-                                        agents.push(agent);
-                                    }
-                                }
-                            }
-                        }
-                    }
+    /// Fills in the reachable summits and path counts of every cell, highest first so each cell's
+    /// uphill neighbours are always done before it.
+    pub fn trails(&self, start: usize, target: usize) -> Trails {
+        let (width, height) = (self.heights.width, self.heights.height);
+        let mut cells: Vec<(usize, usize)> = (0..height)
+            .flat_map(|y| (0..width).map(move |x| (x, y)))
+            .filter(|(x, y)| *self.heights.get_unsafe(*x, *y) <= target)
+            .collect();
+        let trailheads = cells
+            .iter()
+            .copied()
+            .filter(|(x, y)| *self.heights.get_unsafe(*x, *y) == start)
+            .collect();
+        cells.sort_by_key(|(x, y)| std::cmp::Reverse(*self.heights.get_unsafe(*x, *y)));
+        let summits: Vec<(usize, usize)> = cells
+            .iter()
+            .copied()
+            .filter(|(x, y)| *self.heights.get_unsafe(*x, *y) == target)
+            .collect();
+        let words = summits.len().div_ceil(64);
+        let mut reachable = vec![0_u64; width * height * words];
+        let mut paths = vec![0_u64; width * height];
+        for (summit, (x, y)) in summits.iter().enumerate() {
+            reachable[(y * width + x) * words + summit / 64] |= 1 << (summit % 64);
+            paths[y * width + x] = 1;
+        }
+        for (x, y) in &cells {
+            if *self.heights.get_unsafe(*x, *y) == target {
+                continue;
+            }
+            let cell = y * width + x;
+            for (nx, ny) in self.uphill(*x, *y) {
+                let next = ny * width + nx;
+                paths[cell] = paths[cell].saturating_add(paths[next]);
+                for word in 0..words {
+                    reachable[cell * words + word] |= reachable[next * words + word];
                 }
             }
         }
+        Trails {
+            width,
+            trailheads,
+            summits,
+            reachable,
+            words,
+            paths,
+        }
     }
-    Some(paths.len())
 }
 
-pub fn part_two(input: &str) -> Option<usize> {
-    let world: XYWorld<usize> = XYWorld::<usize>::from_str(input);
-    let mut paths: Vec<Vec<(usize, usize)>> = Vec::new();
-    for x in 0..world.width {
-        for y in 0..world.height {
-            let height = world.get_unsafe(x, y);
-            if *height == 0 {
-                let mut agents: Vec<Agent> = vec![];
-                for (dx, dy) in DIRS {
-                    let agent = Agent::with_path(x, y, dx, dy, vec![(x, y)]);
-                    agents.push(agent);
-                }
-                while let Some(mut agent) = agents.pop() {
-                    let Agent {
-                        x,
-                        y,
-                        dx,
-                        dy,
-                        ref mut path,
-                    } = agent;
-                    if let (Some(nx), Some(ny)) =
-                        (x.checked_add_signed(dx), y.checked_add_signed(dy))
-                    {
-                        if world.contains(nx as isize, ny as isize) {
-                            let current = world.get_unsafe(x, y);
-                            let height = world.get_unsafe(nx, ny);
-                            if height.checked_sub(*current).is_some_and(|diff| diff == 1) {
-                                path.push((nx, ny));
-                                if path.len() == 10 {
-                                    paths.push(path.to_vec());
-                                } else {
-                                    for (dx, dy) in DIRS {
-                                        let agent = Agent::with_path(nx, ny, dx, dy, path.clone());
-                                        agents.push(agent);
-                                    }
-                                }
-                            }
-                        }
-                    }
-                }
-            }
-        }
+impl Trails {
+    pub fn trailheads(&self) -> &[(usize, usize)] {
+        &self.trailheads
     }
-    let mut grouped: HashMap<(usize, usize), HashSet<Vec<(usize, usize)>>> = HashMap::new();
-    for path in paths.iter() {
-        let id = path.first().unwrap();
 
-        let found = grouped.entry(*id).or_insert(HashSet::new());
-        found.insert(path.to_vec());
+    /// Summits reachable from `(x, y)`.
+    pub fn summits(&self, x: usize, y: usize) -> impl Iterator<Item = (usize, usize)> + '_ {
+        let cell = y * self.width + x;
+        self.summits
+            .iter()
+            .enumerate()
+            .filter(move |(summit, _)| {
+                self.reachable[cell * self.words + summit / 64] & (1 << (summit % 64)) != 0
+            })
+            .map(|(_, position)| *position)
     }
-    let mut len = 0;
-    for (_, v) in &grouped {
-        len += v.len();
+
+    /// Number of distinct summits reachable from `(x, y)`.
+    pub fn score(&self, x: usize, y: usize) -> usize {
+        let cell = y * self.width + x;
+        self.reachable[cell * self.words..(cell + 1) * self.words]
+            .iter()
+            .map(|word| word.count_ones() as usize)
+            .sum()
     }
-    Some(len)
+
+    /// Number of distinct paths from `(x, y)` to any summit, saturating at `u64::MAX`.
+    pub fn rating(&self, x: usize, y: usize) -> u64 {
+        self.paths[y * self.width + x]
+    }
+
+    pub fn total_score(&self) -> usize {
+        self.trailheads
+            .iter()
+            .map(|(x, y)| self.score(*x, *y))
+            .sum()
+    }
+
+    /// Sum of every trailhead rating, saturating at `u64::MAX` like the ratings themselves.
+    pub fn total_rating(&self) -> u64 {
+        self.trailheads
+            .iter()
+            .map(|(x, y)| self.rating(*x, *y))
+            .fold(0, u64::saturating_add)
+    }
+}
+
+pub fn part_one(input: &str) -> Option<usize> {
+    let map = TopoMap::parse(input, 1).ok()?;
+    Some(map.trails(0, 9).total_score())
+}
+
+pub fn part_two(input: &str) -> Option<u64> {
+    let map = TopoMap::parse(input, 1).ok()?;
+    Some(map.trails(0, 9).total_rating())
 }
 
 #[cfg(test)]
@@ -153,4 +181,29 @@ mod tests {
         let result = part_two(&advent_of_code::template::read_file("examples", DAY));
         assert_eq!(result, Some(81));
     }
+
+    #[test]
+    fn test_trails() {
+        let map = TopoMap::parse("0123\n1234\n8765\n9876", 1).unwrap();
+        let trails = map.trails(0, 9);
+        assert_eq!(trails.trailheads(), &[(0, 0)]);
+        assert_eq!(trails.summits(0, 0).collect::<Vec<_>>(), vec![(0, 3)]);
+        assert_eq!(trails.score(0, 0), 1);
+        assert_eq!(trails.rating(0, 0), 16);
+
+        let trails = map.trails(2, 6);
+        assert_eq!(trails.trailheads(), &[(2, 0), (1, 1)]);
+        assert_eq!(trails.total_score(), 4);
+
+        let evens = TopoMap::parse("0246\n2468\n1357", 2).unwrap();
+        let trails = evens.trails(0, 8);
+        assert_eq!(trails.total_score(), 1);
+        assert_eq!(trails.total_rating(), 4);
+        assert_eq!(evens.trails(1, 7).total_rating(), 1);
+        assert_eq!(TopoMap::parse("01", 0).err(), Some(TopoError::ZeroStep));
+        assert_eq!(
+            TopoMap::parse("", 1).err(),
+            Some(TopoError::Grid(GridError::Empty))
+        );
+    }
 }
@@ -1,69 +1,230 @@
 use std::collections::HashMap;
 
-use itertools::Itertools;
-use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
-
 advent_of_code::solution!(11);
 
-pub fn part_one(input: &str) -> Option<usize> {
-    let mut line: Vec<String> = input
-        .split_ascii_whitespace()
-        .map(|str| str.to_string())
-        .collect();
-    for _ in 0..25 {
-        let mut next_line: Vec<String> = vec![];
-        for num_str in line {
-            let length = num_str.chars().count();
-            if num_str == "0" {
-                next_line.push("1".to_string());
-            } else if length % 2 == 0 {
-                let (left, right) = num_str.split_at(length / 2);
-                next_line.push(left.parse::<usize>().unwrap().to_string());
-                next_line.push(right.parse::<usize>().unwrap().to_string());
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EvolveError {
+    /// A rule produced a stone too large for a u64, from this stone.
+    StoneOverflow(u64),
+    /// More stones than fit in a u128.
+    CountOverflow,
+}
+
+/// What a single stone turns into after one blink.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Replacement {
+    One(u64),
+    Two(u64, u64),
+}
+
+/// A transformation rule, rules are tried in order and the first to return a replacement wins.
+pub trait Rule {
+    fn apply(&self, stone: u64) -> Result<Option<Replacement>, EvolveError>;
+}
+
+impl<F> Rule for F
+where
+    F: Fn(u64) -> Result<Option<Replacement>, EvolveError>,
+{
+    fn apply(&self, stone: u64) -> Result<Option<Replacement>, EvolveError> {
+        self(stone)
+    }
+}
+
+pub fn digits(stone: u64) -> u32 {
+    stone.checked_ilog10().unwrap_or(0) + 1
+}
+
+/// `0` becomes `1`.
+pub struct ZeroToOne;
+
+impl Rule for ZeroToOne {
+    fn apply(&self, stone: u64) -> Result<Option<Replacement>, EvolveError> {
+        Ok((stone == 0).then_some(Replacement::One(1)))
+    }
+}
+
+/// A stone with an even number of digits splits into its left and right halves.
+pub struct SplitEvenDigits;
+
+impl Rule for SplitEvenDigits {
+    fn apply(&self, stone: u64) -> Result<Option<Replacement>, EvolveError> {
+        let digits = digits(stone);
+        if !digits.is_multiple_of(2) {
+            return Ok(None);
+        }
+        let half = 10_u64.pow(digits / 2);
+        Ok(Some(Replacement::Two(stone / half, stone % half)))
+    }
+}
+
+/// Any stone is multiplied by the factor.
+pub struct Multiply(pub u64);
+
+impl Rule for Multiply {
+    fn apply(&self, stone: u64) -> Result<Option<Replacement>, EvolveError> {
+        let next = stone
+            .checked_mul(self.0)
+            .ok_or(EvolveError::StoneOverflow(stone))?;
+        Ok(Some(Replacement::One(next)))
+    }
+}
+
+/// Counts stones after a number of blinks, memoizing `(stone, blinks)` so the cache is shared by
+/// every stone counted with the same evolver.
+pub struct Evolver {
+    rules: Vec<Box<dyn Rule>>,
+    cache: HashMap<(u64, usize), u128>,
+}
+
+impl Evolver {
+    pub fn new(rules: Vec<Box<dyn Rule>>) -> Evolver {
+        Evolver {
+            rules,
+            cache: HashMap::new(),
+        }
+    }
+
+    /// The puzzle rules, zero to one, split even digits, otherwise multiply by 2024.
+    pub fn standard() -> Evolver {
+        Evolver::new(vec![
+            Box::new(ZeroToOne),
+            Box::new(SplitEvenDigits),
+            Box::new(Multiply(2024)),
+        ])
+    }
+
+    /// What `stone` becomes after one blink, unchanged if no rule applies.
+    pub fn blink(&self, stone: u64) -> Result<Replacement, EvolveError> {
+        for rule in &self.rules {
+            if let Some(replacement) = rule.apply(stone)? {
+                return Ok(replacement);
+            }
+        }
+        Ok(Replacement::One(stone))
+    }
+
+    fn cached(&self, stone: u64, blinks: usize) -> Option<u128> {
+        match blinks {
+            0 => Some(1),
+            _ => self.cache.get(&(stone, blinks)).copied(),
+        }
+    }
+
+    /// Number of stones `stone` has become after `blinks`. Works through an explicit stack rather
+    /// than recursing, so thousands of blinks don't exhaust the call stack.
+    pub fn count(&mut self, stone: u64, blinks: usize) -> Result<u128, EvolveError> {
+        // `(stone, blinks, children_done)`, a stone is revisited once its children are cached.
+        let mut stack = vec![(stone, blinks, false)];
+        while let Some((stone, blinks, children_done)) = stack.pop() {
+            if self.cached(stone, blinks).is_some() {
+                continue;
+            }
+            let replacement = self.blink(stone)?;
+            if children_done {
+                let count = match replacement {
+                    Replacement::One(next) => self.cached(next, blinks - 1).map(Some),
+                    Replacement::Two(left, right) => self
+                        .cached(left, blinks - 1)
+                        .zip(self.cached(right, blinks - 1))
+                        .map(|(left, right)| left.checked_add(right)),
+                };
+                let count = count
+                    .expect("children are counted first")
+                    .ok_or(EvolveError::CountOverflow)?;
+                self.cache.insert((stone, blinks), count);
             } else {
-                let parsed = num_str.parse::<usize>().unwrap() * 2024;
-                next_line.push(parsed.to_string());
+                stack.push((stone, blinks, true));
+                match replacement {
+                    Replacement::One(next) => stack.push((next, blinks - 1, false)),
+                    Replacement::Two(left, right) => {
+                        stack.push((left, blinks - 1, false));
+                        stack.push((right, blinks - 1, false));
+                    }
+                }
             }
         }
-        line = next_line;
-    }
-    Some(line.len())
-}
-
-pub fn part_two(input: &str) -> Option<usize> {
-    let init_line: Vec<&str> = input.split_ascii_whitespace().collect();
-    let lines = init_line.par_iter().flat_map(|stone| {
-        let mut line: HashMap<String, usize> = HashMap::new();
-        line.insert(stone.to_string(), 1);
-        for _ in 0..75 {
-            let line_iter = line.iter_mut();
-            let mut next_line: HashMap<String, usize> = HashMap::new();
-            for (next_stone, v) in line_iter {
-                let length = next_stone.len();
-                if *next_stone == "0".to_string() {
-                    *next_line.entry("1".to_string()).or_insert(0) += *v;
-                } else if length % 2 == 0 {
-                    let (left, right) = next_stone.split_at(length / 2);
-                    *next_line
-                        .entry(left.parse::<usize>().unwrap().to_string())
-                        .or_insert(0) += *v;
-                    *next_line
-                        .entry(right.parse::<usize>().unwrap().to_string())
-                        .or_insert(0) += *v;
-                } else {
-                    let parsed = next_stone.parse::<usize>().unwrap() * 2024;
-                    *next_line.entry(parsed.to_string()).or_insert(0) += *v;
+        Ok(self.cached(stone, blinks).expect("counted above"))
+    }
+
+    pub fn count_all(&mut self, stones: &[u64], blinks: usize) -> Result<u128, EvolveError> {
+        stones.iter().try_fold(0_u128, |total, stone| {
+            total
+                .checked_add(self.count(*stone, blinks)?)
+                .ok_or(EvolveError::CountOverflow)
+        })
+    }
+}
+
+/// The line of stones as a multiset, since order never affects the count.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Stones {
+    counts: HashMap<u64, u128>,
+}
+
+impl Stones {
+    pub fn parse(input: &str) -> Option<Stones> {
+        let mut stones = Stones::default();
+        for stone in input.split_ascii_whitespace() {
+            *stones.counts.entry(stone.parse().ok()?).or_default() += 1;
+        }
+        Some(stones)
+    }
+
+    pub fn stones(&self) -> Vec<u64> {
+        self.counts.keys().copied().collect()
+    }
+
+    /// How many of each distinct stone there are.
+    pub fn counts(&self) -> &HashMap<u64, u128> {
+        &self.counts
+    }
+
+    pub fn len(&self) -> Result<u128, EvolveError> {
+        self.counts.values().try_fold(0_u128, |total, count| {
+            total.checked_add(*count).ok_or(EvolveError::CountOverflow)
+        })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.counts.is_empty()
+    }
+
+    pub fn blink(&mut self, evolver: &Evolver) -> Result<(), EvolveError> {
+        let mut next: HashMap<u64, u128> = HashMap::with_capacity(self.counts.len());
+        let mut add = |stone: u64, count: u128| {
+            let entry = next.entry(stone).or_default();
+            *entry = entry.checked_add(count).ok_or(EvolveError::CountOverflow)?;
+            Ok(())
+        };
+        for (stone, count) in &self.counts {
+            match evolver.blink(*stone)? {
+                Replacement::One(next) => add(next, *count)?,
+                Replacement::Two(left, right) => {
+                    add(left, *count)?;
+                    add(right, *count)?;
                 }
             }
-            line = next_line;
         }
-        line
-    });
-    let mut final_map: HashMap<String, usize> = HashMap::new();
-    for (k, v) in lines.collect_vec_list().into_iter().flatten() {
-        *final_map.entry(k).or_insert(0) += v;
+        self.counts = next;
+        Ok(())
     }
-    final_map.values().sum1::<usize>()
+}
+
+fn solve(input: &str, blinks: usize) -> Option<u128> {
+    let stones: Vec<u64> = input
+        .split_ascii_whitespace()
+        .map(|stone| stone.parse().ok())
+        .collect::<Option<_>>()?;
+    Evolver::standard().count_all(&stones, blinks).ok()
+}
+
+pub fn part_one(input: &str) -> Option<u128> {
+    solve(input, 25)
+}
+
+pub fn part_two(input: &str) -> Option<u128> {
+    solve(input, 75)
 }
 
 #[cfg(test)]
@@ -75,4 +236,47 @@ mod tests {
         let result = part_one(&advent_of_code::template::read_file("examples", DAY));
         assert_eq!(result, Some(55312));
     }
+
+    #[test]
+    fn test_part_two() {
+        let result = part_two(&advent_of_code::template::read_file("examples", DAY));
+        assert_eq!(result, Some(65601038650482));
+    }
+
+    #[test]
+    fn test_multiset_matches_cache() {
+        let input = advent_of_code::template::read_file("examples", DAY);
+        let mut stones = Stones::parse(&input).unwrap();
+        let evolver = Evolver::standard();
+        for _ in 0..6 {
+            stones.blink(&evolver).unwrap();
+        }
+        assert_eq!(stones.len(), Ok(22));
+        assert_eq!(digits(0), 1);
+        assert_eq!(digits(1000), 4);
+        assert_eq!(evolver.blink(253000), Ok(Replacement::Two(253, 0)));
+
+        let mut evolver = Evolver::standard();
+        assert_eq!(evolver.count(0, 1000), Err(EvolveError::CountOverflow));
+        assert!(evolver.count(0, 150).is_ok());
+
+        let mut unchanging = Evolver::new(vec![Box::new(ZeroToOne)]);
+        assert_eq!(unchanging.count(5, 50_000), Ok(1));
+    }
+
+    #[test]
+    fn test_custom_rules() {
+        let halve = |stone: u64| {
+            Ok(stone
+                .is_multiple_of(2)
+                .then_some(Replacement::One(stone / 2)))
+        };
+        let mut evolver = Evolver::new(vec![Box::new(halve), Box::new(Multiply(3))]);
+        assert_eq!(evolver.blink(12), Ok(Replacement::One(6)));
+        assert_eq!(evolver.count(7, 10), Ok(1));
+        assert_eq!(
+            evolver.count(u64::MAX, 1),
+            Err(EvolveError::StoneOverflow(u64::MAX))
+        );
+    }
 }
@@ -1,93 +1,75 @@
-use itertools::Itertools;
+use advent_of_code::{
+    math::{cheapest_combination, MathError},
+    parse::{sections, ParseError},
+};
 
 advent_of_code::solution!(13);
 
+/// Presses of button A cost 3 tokens and presses of button B cost 1.
+const TOKEN_COSTS: (i64, i64) = (3, 1);
+const PRIZE_OFFSET: i64 = 10_000_000_000_000;
+
 #[derive(Debug)]
 struct ClawMachine {
-    button_a: (isize, isize),
-    button_b: (isize, isize),
-    prize: (isize, isize),
+    button_a: (i64, i64),
+    button_b: (i64, i64),
+    prize: (i64, i64),
 }
 
 impl ClawMachine {
-    fn solve(&self) -> isize {
-        let x1 = self.button_a.0;
-        let x2 = self.button_a.1;
-        let y1 = self.button_b.0;
-        let y2 = self.button_b.1;
-        let z1 = self.prize.0;
-        let z2 = self.prize.1;
-        let b = (z2 * x1 - z1 * x2) / (y2 * x1 - y1 * x2);
-        let a = (z1 - b * y1) / x1;
-        if (x1 * a + y1 * b, x2 * a + y2 * b) != (z1, z2) {
-            return 0;
-        }
-        a * 3 + b
+    /// Fewest tokens to win the prize after moving it by `offset` on both axes, `None` when it
+    /// can't be won.
+    fn tokens(&self, offset: i64, costs: (i64, i64)) -> Result<Option<i64>, MathError> {
+        let prize = (
+            self.prize
+                .0
+                .checked_add(offset)
+                .ok_or(MathError::Overflow)?,
+            self.prize
+                .1
+                .checked_add(offset)
+                .ok_or(MathError::Overflow)?,
+        );
+        let Some((a, b)) = cheapest_combination(self.button_a, self.button_b, prize, costs)? else {
+            return Ok(None);
+        };
+        a.checked_mul(costs.0)
+            .zip(b.checked_mul(costs.1))
+            .and_then(|(a, b)| a.checked_add(b))
+            .ok_or(MathError::Overflow)
+            .map(Some)
     }
+}
 
-    fn solve_10000000000000(&self) -> isize {
-        let x1 = self.button_a.0;
-        let x2 = self.button_a.1;
-        let y1 = self.button_b.0;
-        let y2 = self.button_b.1;
-        let z1 = self.prize.0 + 10000000000000;
-        let z2 = self.prize.1 + 10000000000000;
-        let b = (z2 * x1 - z1 * x2) / (y2 * x1 - y1 * x2);
-        let a = (z1 - b * y1) / x1;
-        if (x1 * a + y1 * b, x2 * a + y2 * b) != (z1, z2) {
-            return 0;
-        }
-        a * 3 + b
+fn total_tokens(input: &str, offset: i64) -> Option<i64> {
+    let mut result: i64 = 0;
+    for machine in parse(input).ok()? {
+        result = result.checked_add(machine.tokens(offset, TOKEN_COSTS).ok()?.unwrap_or(0))?;
     }
+    Some(result)
 }
 
-fn parse(input: &str) -> Vec<ClawMachine> {
+fn parse(input: &str) -> Result<Vec<ClawMachine>, ParseError> {
     let mut claw_machines = Vec::new();
-    for line in &input.lines().filter(|line| !line.is_empty()).chunks(3) {
-        if let Some((a_line, b_line, prize_line)) = line.collect_tuple::<(&str, &str, &str)>() {
-            let (ax, ay) = a_line
-                .strip_prefix("Button A: X+")
-                .unwrap()
-                .split_once(", Y+")
-                .unwrap();
-            let (bx, by) = b_line
-                .strip_prefix("Button B: X+")
-                .unwrap()
-                .split_once(", Y+")
-                .unwrap();
-
-            let (px, py) = prize_line
-                .strip_prefix("Prize: X=")
-                .unwrap()
-                .split_once(", Y=")
-                .unwrap();
-
-            let machine = ClawMachine {
-                button_a: (ax.parse().unwrap(), ay.parse().unwrap()),
-                button_b: (bx.parse().unwrap(), by.parse().unwrap()),
-                prize: (px.parse().unwrap(), py.parse().unwrap()),
-            };
-            claw_machines.push(machine);
-        }
+    for section in sections(input) {
+        let [ax, ay] = section.line(0)?.integers_n()?;
+        let [bx, by] = section.line(1)?.integers_n()?;
+        let [px, py] = section.line(2)?.integers_n()?;
+        claw_machines.push(ClawMachine {
+            button_a: (ax, ay),
+            button_b: (bx, by),
+            prize: (px, py),
+        });
     }
-
-    claw_machines
+    Ok(claw_machines)
 }
 
-pub fn part_one(input: &str) -> Option<isize> {
-    let mut result = 0;
-    for machine in parse(input) {
-        result += machine.solve();
-    }
-    Some(result)
+pub fn part_one(input: &str) -> Option<i64> {
+    total_tokens(input, 0)
 }
 
-pub fn part_two(input: &str) -> Option<isize> {
-    let mut result = 0;
-    for machine in parse(input) {
-        result += machine.solve_10000000000000();
-    }
-    Some(result)
+pub fn part_two(input: &str) -> Option<i64> {
+    total_tokens(input, PRIZE_OFFSET)
 }
 
 #[cfg(test)]
@@ -99,4 +81,33 @@ mod tests {
         let result = part_one(&advent_of_code::template::read_file("examples", DAY));
         assert_eq!(result, Some(480));
     }
+
+    #[test]
+    fn test_incomplete_machine() {
+        assert!(parse("Button A: X+94, Y+34\nButton B: X+22").is_err());
+    }
+
+    #[test]
+    fn test_part_two() {
+        let result = part_two(&advent_of_code::template::read_file("examples", DAY));
+        assert_eq!(result, Some(875318608908));
+    }
+
+    #[test]
+    fn test_collinear_buttons() {
+        // both buttons move along the diagonal, so the cheapest mix has to be searched for
+        let machine = ClawMachine {
+            button_a: (3, 3),
+            button_b: (2, 2),
+            prize: (12, 12),
+        };
+        assert_eq!(machine.tokens(0, TOKEN_COSTS), Ok(Some(6)));
+        assert_eq!(machine.tokens(0, (1, 3)), Ok(Some(4)));
+        assert_eq!(machine.tokens(1, TOKEN_COSTS), Ok(Some(8)));
+        let off_line = ClawMachine {
+            prize: (12, 13),
+            ..machine
+        };
+        assert_eq!(off_line.tokens(0, TOKEN_COSTS), Ok(None));
+    }
 }
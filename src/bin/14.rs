@@ -1,184 +1,266 @@
-use std::collections::{HashMap, HashSet};
+use std::{fmt::Write, fs, io, ops::Range, path::Path};
 
-use advent_of_code::Rect;
+use advent_of_code::{
+    grid::Connectivity,
+    math::{crt, lcm},
+    parse::{lines, ParseError},
+    XYWorld,
+};
 
 advent_of_code::solution!(14);
 
-#[derive(Debug)]
-pub struct Robot {
-    pos: (isize, isize),
-    vel: (isize, isize),
+#[cfg(test)]
+const DIMENSIONS: (i64, i64) = (11, 7);
+#[cfg(not(test))]
+const DIMENSIONS: (i64, i64) = (101, 103);
+
+/// Spread of the robots in a single frame.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FrameStats {
+    pub mean: (f64, f64),
+    pub variance: (f64, f64),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FrameFormat {
+    /// Robot counts per tile like the puzzle draws them.
+    Text,
+    /// A plain black and white bitmap of occupied tiles.
+    Pbm,
+    /// A plain greyscale map of robot counts per tile.
+    Pgm,
+}
+
+/// How part two looks for the frame with the Christmas tree.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TreeSearch {
+    /// Steps a second at a time until a large connected cluster of robots appears.
+    Cluster,
+    /// Every robot's x position repeats each `width` seconds and its y position each `height`
+    /// seconds. The tree is drawn when the robots are least spread out, so find the tick with
+    /// the lowest variance on each axis and combine them with the chinese remainder theorem.
+    Variance,
+}
+
+/// Every robot on the floor, stored as one array per component so a tick is a pass over flat
+/// slices.
+#[derive(Debug, Clone)]
+pub struct Swarm {
+    xs: Vec<i64>,
+    ys: Vec<i64>,
+    dxs: Vec<i64>,
+    dys: Vec<i64>,
+    width: i64,
+    height: i64,
+    tick: i64,
 }
 
-impl Robot {
-    pub fn tick(&mut self, width: isize, height: isize) {
-        let mut new_x = self.pos.0 + self.vel.0;
-        let mut new_y = self.pos.1 + self.vel.1;
-        if new_x < 0 {
-            new_x = width + new_x;
+/// The tick along one axis, within a single period, where positions have the least variance.
+fn quietest_tick(positions: &[i64], velocities: &[i64], size: i64) -> i64 {
+    let n = positions.len() as i64;
+    (0..size)
+        .min_by_key(|tick| {
+            let (sum, sum_squares) =
+                positions
+                    .iter()
+                    .zip(velocities)
+                    .fold((0, 0), |(sum, squares), (pos, vel)| {
+                        let x = (pos + vel * tick).rem_euclid(size);
+                        (sum + x, squares + x * x)
+                    });
+            // n² times the variance, which orders the same way
+            n * sum_squares - sum * sum
+        })
+        .unwrap_or(0)
+}
+
+fn variance(values: &[i64]) -> (f64, f64) {
+    let n = values.len().max(1) as f64;
+    let mean = values.iter().sum::<i64>() as f64 / n;
+    let variance = values
+        .iter()
+        .map(|v| (*v as f64 - mean).powi(2))
+        .sum::<f64>()
+        / n;
+    (mean, variance)
+}
+
+impl Swarm {
+    pub fn new(width: i64, height: i64) -> Swarm {
+        Swarm {
+            xs: vec![],
+            ys: vec![],
+            dxs: vec![],
+            dys: vec![],
+            width,
+            height,
+            tick: 0,
         }
-        if new_y < 0 {
-            new_y = height + new_y;
+    }
+
+    pub fn parse(input: &str, (width, height): (i64, i64)) -> Result<Swarm, ParseError> {
+        let mut swarm = Swarm::new(width, height);
+        for line in lines(input) {
+            let [x, y, dx, dy] = line.integers_n()?;
+            swarm.push((x, y), (dx, dy));
         }
+        Ok(swarm)
+    }
 
-        if new_x >= width {
-            new_x -= width;
+    pub fn push(&mut self, (x, y): (i64, i64), (dx, dy): (i64, i64)) {
+        self.xs.push(x.rem_euclid(self.width));
+        self.ys.push(y.rem_euclid(self.height));
+        self.dxs.push(dx);
+        self.dys.push(dy);
+    }
+
+    pub fn tick(&self) -> i64 {
+        self.tick
+    }
+
+    /// Moves every robot `steps` seconds forward, wrapping around the edges. Positions repeat
+    /// every `width` and `height` seconds so the cost doesn't depend on `steps`.
+    pub fn advance(&mut self, steps: i64) {
+        let (sx, sy) = (steps.rem_euclid(self.width), steps.rem_euclid(self.height));
+        for (x, dx) in self.xs.iter_mut().zip(&self.dxs) {
+            *x = (*x + dx.rem_euclid(self.width) * sx) % self.width;
         }
-        if new_y >= height {
-            new_y -= height;
+        for (y, dy) in self.ys.iter_mut().zip(&self.dys) {
+            *y = (*y + dy.rem_euclid(self.height) * sy) % self.height;
         }
-
-        self.pos.0 = new_x;
-        self.pos.1 = new_y;
+        self.tick += steps;
     }
-}
 
-pub fn parse(input: &str) -> Vec<Robot> {
-    let mut robots = vec![];
-    for line in input.lines() {
-        if let Some((left, right)) = line.split_once(' ') {
-            if let (Some((x_str, y_str)), Some((dx_str, dy_str))) = (
-                left.strip_prefix("p=")
-                    .and_then(|p_str| p_str.split_once(",")),
-                right
-                    .strip_prefix("v=")
-                    .and_then(|v_str| v_str.split_once(",")),
-            ) {
-                let x = x_str.parse::<isize>().unwrap();
-                let y = y_str.parse::<isize>().unwrap();
-                let dx = dx_str.parse::<isize>().unwrap();
-                let dy = dy_str.parse::<isize>().unwrap();
-                let robot = Robot {
-                    pos: (x, y),
-                    vel: (dx, dy),
-                };
-                robots.push(robot);
-            } else {
-                panic!();
+    /// Robots in the top left, top right, bottom left and bottom right quadrants, robots on the
+    /// middle row or column aren't in any.
+    pub fn quadrants(&self) -> [usize; 4] {
+        let (mid_x, mid_y) = (self.width / 2, self.height / 2);
+        let mut quadrants = [0; 4];
+        for (x, y) in self.xs.iter().zip(&self.ys) {
+            if *x == mid_x || *y == mid_y {
+                continue;
             }
-        } else {
-            panic!();
+            quadrants[(*x > mid_x) as usize + 2 * (*y > mid_y) as usize] += 1;
         }
+        quadrants
     }
-    robots
-}
 
-pub fn part_one(input: &str) -> Option<u32> {
-    let mut robots = parse(input);
-    #[cfg(test)]
-    let dimensions = (11, 7);
-    #[cfg(not(test))]
-    let dimensions = (101, 103);
+    pub fn safety_factor(&self) -> usize {
+        self.quadrants().iter().product()
+    }
 
-    for _ in 0..100 {
-        for robot in robots.iter_mut() {
-            robot.tick(dimensions.0, dimensions.1);
+    pub fn stats(&self) -> FrameStats {
+        let (mean_x, variance_x) = variance(&self.xs);
+        let (mean_y, variance_y) = variance(&self.ys);
+        FrameStats {
+            mean: (mean_x, mean_y),
+            variance: (variance_x, variance_y),
         }
     }
-    let top_right = Rect::new((0, 0), (dimensions.0 / 2 - 1, dimensions.1 / 2 - 1));
-    let top_left = Rect::new(
-        (dimensions.0 / 2 + 1, 0),
-        (dimensions.0, dimensions.1 / 2 - 1),
-    );
-    let bot_left = Rect::new(
-        (0, dimensions.1 / 2 + 1),
-        (dimensions.0 / 2 - 1, dimensions.1 - 1),
-    );
-    let bot_right = Rect::new(
-        (dimensions.0 / 2 + 1, dimensions.1 / 2 + 1),
-        (dimensions.0, dimensions.1 - 1),
-    );
-    let mut top_left_quadrant = 0;
-    let mut top_right_quadrant = 0;
-    let mut bot_left_quadrant = 0;
-    let mut bot_right_quadrant = 0;
-    for robot in &robots {
-        let (x, y) = robot.pos;
-        if top_left.includes(x, y) {
-            top_left_quadrant += 1;
-        }
-        if top_right.includes(x, y) {
-            top_right_quadrant += 1;
-        }
-        if bot_left.includes(x, y) {
-            bot_left_quadrant += 1;
+
+    /// Number of robots on each tile.
+    pub fn counts(&self) -> XYWorld<usize> {
+        let mut counts = XYWorld::<usize>::filled(self.width as usize, self.height as usize, 0);
+        for (x, y) in self.xs.iter().zip(&self.ys) {
+            if let Some(count) = counts.get_mut(*x as isize, *y as isize) {
+                *count += 1;
+            }
         }
-        if bot_right.includes(x, y) {
-            bot_right_quadrant += 1;
+        counts
+    }
+
+    pub fn to_text(&self) -> String {
+        self.counts()
+            .map(|count| match count {
+                0 => '.',
+                1..=9 => char::from_digit(*count as u32, 10).unwrap(),
+                _ => '+',
+            })
+            .to_string()
+    }
+
+    fn to_netpbm(&self, header: &str, value: impl Fn(usize) -> usize) -> String {
+        let counts = self.counts();
+        let mut image = String::from(header);
+        for y in 0..counts.height {
+            for x in 0..counts.width {
+                let separator = if x + 1 == counts.width { "\n" } else { " " };
+                write!(image, "{}{}", value(*counts.get_unsafe(x, y)), separator).unwrap();
+            }
         }
+        image
+    }
+
+    /// The frame as a plain PBM image, black pixels are occupied tiles.
+    pub fn to_pbm(&self) -> String {
+        let header = format!("P1\n{} {}\n", self.width, self.height);
+        self.to_netpbm(&header, |count| count.min(1))
+    }
+
+    /// The frame as a plain PGM image, brighter pixels hold more robots.
+    pub fn to_pgm(&self) -> String {
+        let max = self.counts().values().max().copied().unwrap_or(0).max(1);
+        let header = format!("P2\n{} {}\n{}\n", self.width, self.height, max);
+        self.to_netpbm(&header, |count| count)
     }
-    Some(top_left_quadrant * top_right_quadrant * bot_left_quadrant * bot_right_quadrant)
-}
 
-pub fn part_two(input: &str) -> Option<u32> {
-    let mut robots = parse(input);
-    #[cfg(test)]
-    let dimensions = (11, 7);
-    #[cfg(not(test))]
-    let dimensions = (101, 103);
-
-    let mut second = 0;
-    loop {
-        second += 1;
-        for robot in robots.iter_mut() {
-            robot.tick(dimensions.0, dimensions.1);
+    /// Writes one file per tick in `ticks` to `directory`, named after the tick, leaving the swarm
+    /// at the end of the range.
+    pub fn export_frames(
+        &mut self,
+        directory: &Path,
+        ticks: Range<i64>,
+        format: FrameFormat,
+    ) -> io::Result<()> {
+        fs::create_dir_all(directory)?;
+        self.advance(ticks.start - self.tick);
+        for tick in ticks {
+            let (contents, extension) = match format {
+                FrameFormat::Text => (self.to_text(), "txt"),
+                FrameFormat::Pbm => (self.to_pbm(), "pbm"),
+                FrameFormat::Pgm => (self.to_pgm(), "pgm"),
+            };
+            fs::write(directory.join(format!("{tick:05}.{extension}")), contents)?;
+            self.advance(1);
         }
-        let mut seen: HashSet<(isize, isize)> = HashSet::new();
-        let mut robot_iter = robots.iter();
-        let mut connected: HashMap<(isize, isize), usize> = HashMap::new();
-        while let Some(robot) = robot_iter.next() {
-            if !seen.contains(&robot.pos) {
-                let mut agents: Vec<(isize, isize)> = vec![robot.pos];
-                while let Some((x, y)) = agents.pop() {
-                    if !seen.contains(&(x, y)) {
-                        seen.insert((x, y));
-                        *connected.entry(robot.pos).or_insert(0) += 1;
-                        if let Some(_) = robots.iter().find(|other_robot| {
-                            other_robot.pos.0 - 1 == x && other_robot.pos.1 == y
-                        }) {
-                            agents.push((x - 1, y));
-                        }
-                        if let Some(_) = robots.iter().find(|other_robot| {
-                            other_robot.pos.0 + 1 == x && other_robot.pos.1 == y
-                        }) {
-                            agents.push((x + 1, y));
-                        }
-                        if let Some(_) = robots.iter().find(|other_robot| {
-                            other_robot.pos.0 == x && other_robot.pos.1 - 1 == y
-                        }) {
-                            agents.push((x, y - 1));
-                        }
-                        if let Some(_) = robots.iter().find(|other_robot| {
-                            other_robot.pos.0 == x && other_robot.pos.1 + 1 == y
-                        }) {
-                            agents.push((x, y + 1));
-                        }
+        Ok(())
+    }
+
+    /// The first tick that draws the tree, counting the current one. The cluster search leaves the
+    /// swarm on that tick, or a full period of positions later if no tree is drawn.
+    pub fn find_tree(&mut self, search: TreeSearch) -> Option<i64> {
+        match search {
+            TreeSearch::Cluster => {
+                let period = lcm(self.width, self.height).ok()?;
+                for _ in 0..period {
+                    let occupied = self.counts();
+                    let regions = occupied.label_regions_by(Connectivity::Four, |count| *count > 0);
+                    if regions.iter().any(|region| region.area() > 40) {
+                        return Some(self.tick);
                     }
+                    self.advance(1);
                 }
+                None
             }
-        }
-
-        //todo: split robots by X coordinates, if the robot has a partner robot across the line of
-        //symmetry print the result
-        for y in 0..dimensions.1 {
-            for x in 0..dimensions.0 {
-                if robots.iter().filter(|robot| robot.pos == (x, y)).count() > 0 {
-                    print!(
-                        "{}",
-                        robots.iter().filter(|robot| robot.pos == (x, y)).count()
-                    );
-                } else {
-                    print!(".");
-                }
+            TreeSearch::Variance => {
+                let x = quietest_tick(&self.xs, &self.dxs, self.width);
+                let y = quietest_tick(&self.ys, &self.dys, self.height);
+                let (tick, _) = crt(&[(x, self.width), (y, self.height)]).ok()??;
+                Some(self.tick + tick)
             }
-            println!();
-        }
-        println!();
-        if connected.values().find(|connect| **connect > 40).is_some() {
-            break;
         }
     }
-    Some(second)
+}
+
+pub fn part_one(input: &str) -> Option<usize> {
+    let mut swarm = Swarm::parse(input, DIMENSIONS).ok()?;
+    swarm.advance(100);
+    Some(swarm.safety_factor())
+}
+
+pub fn part_two(input: &str) -> Option<i64> {
+    let mut swarm = Swarm::parse(input, DIMENSIONS).ok()?;
+    swarm.find_tree(TreeSearch::Variance)
 }
 
 #[cfg(test)]
@@ -190,4 +272,61 @@ mod tests {
         let result = part_one(&advent_of_code::template::read_file("examples", DAY));
         assert_eq!(result, Some(12));
     }
+
+    #[test]
+    fn test_find_tree_by_variance() {
+        let target = (5, 3);
+        let velocities = [(1, 1), (2, 2), (3, 3), (4, 5), (5, 4), (6, 6)];
+        let mut swarm = Swarm::new(11, 7);
+        for (dx, dy) in velocities {
+            swarm.push((target.0 - dx * 40, target.1 - dy * 40), (dx, dy));
+        }
+        assert_eq!(swarm.clone().find_tree(TreeSearch::Variance), Some(40));
+        swarm.advance(40);
+        assert_eq!(swarm.stats().variance, (0.0, 0.0));
+        // Already on the tree, so the current tick is the answer.
+        assert_eq!(swarm.find_tree(TreeSearch::Variance), Some(40));
+    }
+
+    #[test]
+    fn test_find_tree_without_cluster() {
+        let mut swarm = Swarm::parse(
+            &advent_of_code::template::read_file("examples", DAY),
+            (11, 7),
+        )
+        .unwrap();
+        assert_eq!(swarm.find_tree(TreeSearch::Cluster), None);
+        assert_eq!(swarm.tick(), 77);
+    }
+
+    #[test]
+    fn test_swarm_frames() {
+        let mut swarm = Swarm::parse(
+            &advent_of_code::template::read_file("examples", DAY),
+            (11, 7),
+        )
+        .unwrap();
+        let mut stepped = swarm.clone();
+        for _ in 0..100 {
+            stepped.advance(1);
+        }
+        swarm.advance(100);
+        assert_eq!(swarm.to_text(), stepped.to_text());
+        assert_eq!(swarm.quadrants(), [1, 3, 4, 1]);
+        assert_eq!(
+            swarm.to_text(),
+            "\
+......2..1.
+...........
+1..........
+.11........
+.....1.....
+...12......
+.1....1...."
+        );
+        let pbm = swarm.to_pbm();
+        assert!(pbm.starts_with("P1\n11 7\n0 0 0 0 0 0 1 0 0 1 0\n"));
+        let pgm = swarm.to_pgm();
+        assert!(pgm.starts_with("P2\n11 7\n2\n0 0 0 0 0 0 2 0 0 1 0\n"));
+    }
 }
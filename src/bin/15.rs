@@ -1,270 +1,382 @@
-use advent_of_code::XYWorld;
+use std::fmt::{Display, Write};
+
+use advent_of_code::{
+    grid::{split_section, GridError},
+    XYWorld,
+};
 
 advent_of_code::solution!(15);
 
-fn direction_to_vector(dir: char) -> (isize, isize) {
-    match dir {
-        '^' => (0, -1),
-        '<' => (-1, 0),
-        '>' => (1, 0),
-        'v' => (0, 1),
-        _ => panic!(),
-    }
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    Up,
+    Down,
+    Left,
+    Right,
 }
 
-pub fn part_one(input: &str) -> Option<usize> {
-    let mut buffer = String::new();
-    let mut line_iter = input.lines().into_iter();
-    let mut robot_pos = (0_isize, 0_isize);
-    let mut y: isize = 0;
-    while let Some(line) = line_iter.next() {
-        if !line.is_empty() {
-            if let Some((x, _)) = line.char_indices().find(|(_, ch)| *ch == '@') {
-                robot_pos = (x as isize, y);
-            }
-            buffer.push_str(line);
-            buffer.push_str("\n");
-        } else {
-            break;
+impl Direction {
+    pub fn from_char(ch: char) -> Option<Direction> {
+        match ch {
+            '^' => Some(Direction::Up),
+            'v' => Some(Direction::Down),
+            '<' => Some(Direction::Left),
+            '>' => Some(Direction::Right),
+            _ => None,
         }
-        y += 1;
-    }
-    let mut world = XYWorld::<char>::from_str::<char>(&buffer);
-    buffer.clear();
-    while let Some(line) = line_iter.next() {
-        buffer.push_str(line);
     }
-    for (_, (dx, dy)) in buffer.chars().map(|dir| (dir, direction_to_vector(dir))) {
-        let (x, y) = robot_pos;
-        let (new_x, new_y) = (x + dx, y + dy);
-        if let Some(ch) = world.get_isize(new_x, new_y) {
-            match ch {
-                'O' => {
-                    let (mut barrel_x, mut barrel_y) = (new_x + dx, new_y + dy);
-                    while let Some(barrel) = world.get_isize(barrel_x, barrel_y) {
-                        match barrel {
-                            'O' => {
-                                barrel_x += dx;
-                                barrel_y += dy;
-                            }
-                            '.' => {
-                                *world.get_mut(barrel_x, barrel_y).unwrap() = 'O';
-                                *world.get_mut(x, y).unwrap() = '.';
-                                *world.get_mut(new_x, new_y).unwrap() = '@';
-                                robot_pos = (new_x, new_y);
-                                break;
-                            }
-                            '#' => {
-                                break;
-                            }
-                            _ => panic!(),
-                        }
-                    }
-                }
-                '.' => {
-                    *world.get_mut(x, y).unwrap() = '.';
-                    *world.get_mut(new_x, new_y).unwrap() = '@';
-                    robot_pos = (new_x, new_y);
-                }
-                '#' => {}
-                _ => panic!(),
-            }
+
+    pub fn vector(&self) -> (isize, isize) {
+        match self {
+            Direction::Up => (0, -1),
+            Direction::Down => (0, 1),
+            Direction::Left => (-1, 0),
+            Direction::Right => (1, 0),
         }
     }
 
-    let mut result = 0;
-    for x in 0..world.width {
-        for y in 0..world.height {
-            let ch = world.get_unsafe(x, y);
-            if *ch == 'O' {
-                result += (y * 100) + x
-            }
+    pub fn reverse(&self) -> Direction {
+        match self {
+            Direction::Up => Direction::Down,
+            Direction::Down => Direction::Up,
+            Direction::Left => Direction::Right,
+            Direction::Right => Direction::Left,
         }
     }
-    Some(result)
 }
 
-pub fn part_two(input: &str) -> Option<usize> {
-    let mut buffer = String::new();
-    let mut line_iter = input.lines().into_iter();
-    let mut robot_pos = (0_isize, 0_isize);
-    let mut y: isize = 0;
-    while let Some(line) = line_iter.next() {
-        if !line.is_empty() {
-            for (x, ch) in line.char_indices() {
-                match ch {
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WarehouseError {
+    Grid(GridError),
+    /// A `[` at this position has no matching `]` on the same row.
+    UnclosedBox((usize, usize)),
+    UnexpectedTile((usize, usize), char),
+    MissingRobot,
+    InvalidMove(char),
+    /// Tiles can't be stretched to nothing.
+    ZeroScale,
+}
+
+impl From<GridError> for WarehouseError {
+    fn from(error: GridError) -> Self {
+        WarehouseError::Grid(error)
+    }
+}
+
+/// A box, `x` and `y` are its left-most cell.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Crate {
+    pub x: isize,
+    pub y: isize,
+    pub width: isize,
+}
+
+impl Crate {
+    fn cells(&self) -> impl Iterator<Item = (isize, isize)> + '_ {
+        (self.x..self.x + self.width).map(|x| (x, self.y))
+    }
+}
+
+/// A single robot move, with every box it pushed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Step {
+    pub direction: Direction,
+    /// False when the robot walked into a wall or an immovable stack of boxes.
+    pub moved: bool,
+    pub pushed: Vec<usize>,
+}
+
+pub struct Warehouse {
+    walls: XYWorld<bool>,
+    /// The index into `crates` of the box covering each cell.
+    occupied: XYWorld<Option<usize>>,
+    crates: Vec<Crate>,
+    robot: (isize, isize),
+    log: Vec<Step>,
+}
+
+impl Warehouse {
+    /// Parses a map where `#` is a wall, `@` the robot, `O` a box and `[`, `]` the ends of a wider
+    /// box. Every tile is first stretched `scale` times horizontally, with `O` stretching into a
+    /// single wide box like `[]`.
+    pub fn parse(map: &str, scale: usize) -> Result<Warehouse, WarehouseError> {
+        if scale == 0 {
+            return Err(WarehouseError::ZeroScale);
+        }
+        let world = XYWorld::<char>::parse(map)?;
+        let world = world.expand_horizontal(|ch| {
+            let (first, rest) = match ch {
+                '@' => ('@', '.'),
+                'O' if scale > 1 => ('[', '='),
+                ch => (*ch, *ch),
+            };
+            let mut tile = vec![first; 1];
+            tile.extend(std::iter::repeat_n(rest, scale - 1));
+            if *ch == 'O' && scale > 1 {
+                tile[scale - 1] = ']';
+            }
+            tile
+        });
+        let mut warehouse = Warehouse {
+            walls: world.map(|ch| *ch == '#'),
+            occupied: XYWorld::filled(world.width, world.height, None),
+            crates: vec![],
+            robot: (0, 0),
+            log: vec![],
+        };
+        let mut robot = None;
+        for y in 0..world.height {
+            let mut x = 0;
+            while x < world.width {
+                let width = match world.get_unsafe(x, y) {
                     '@' => {
-                        robot_pos = ((x as isize * 2), y);
-                        buffer.push_str("@.")
+                        robot = Some((x as isize, y as isize));
+                        1
                     }
-                    '#' => buffer.push_str("##"),
-                    'O' => buffer.push_str("[]"),
-                    '.' => buffer.push_str(".."),
-                    _ => panic!("{}", ch),
-                }
+                    'O' => warehouse.add_crate(x, y, 1),
+                    '[' => {
+                        let end = (x + 1..world.width)
+                            .find(|end| *world.get_unsafe(*end, y) != '=')
+                            .filter(|end| *world.get_unsafe(*end, y) == ']')
+                            .ok_or(WarehouseError::UnclosedBox((x, y)))?;
+                        warehouse.add_crate(x, y, end - x + 1)
+                    }
+                    '#' | '.' => 1,
+                    ch => return Err(WarehouseError::UnexpectedTile((x, y), *ch)),
+                };
+                x += width;
             }
-            buffer.push_str("\n");
-        } else {
-            break;
         }
-        y += 1;
+        warehouse.robot = robot.ok_or(WarehouseError::MissingRobot)?;
+        Ok(warehouse)
+    }
+
+    fn add_crate(&mut self, x: usize, y: usize, width: usize) -> usize {
+        let index = self.crates.len();
+        for cx in x..x + width {
+            self.occupied.update_unsafe(cx, y, Some(index));
+        }
+        self.crates.push(Crate {
+            x: x as isize,
+            y: y as isize,
+            width: width as isize,
+        });
+        width
     }
-    let mut world = XYWorld::<char>::from_str::<char>(&buffer);
-    // for x in 0..world.width {
-    //     for y in 0..world.height {
-    //         let ch = world.get_unsafe(x, y);
-    //         if *ch == '@' {
-    //             robot_pos = (x as isize, y as isize);
-    //         }
-    //     }
-    // }
-    buffer.clear();
-    while let Some(line) = line_iter.next() {
-        buffer.push_str(line);
+
+    pub fn robot(&self) -> (isize, isize) {
+        self.robot
     }
-    for (_, (dx, dy)) in buffer.chars().map(|dir| (dir, direction_to_vector(dir))) {
-        let (x, y) = robot_pos;
-        let (new_x, new_y) = (x + dx, y + dy);
-        if let Some(ch) = world.get_isize(new_x, new_y) {
-            match ch {
-                '[' | ']' if dx == 0 => {
-                    let mut selected = if *ch == '[' {
-                        vec![(new_x, new_y), (new_x + 1, new_y)]
-                    } else {
-                        vec![(new_x - 1, new_y), (new_x, new_y)]
-                    };
-                    let mut boxes = if *ch == '[' {
-                        vec![(new_x, new_y), (new_x + 1, new_y)]
-                    } else {
-                        vec![(new_x - 1, new_y), (new_x, new_y)]
-                    };
-                    while let Some((ox, oy)) = selected.pop() {
-                        let nx = ox + dx;
-                        let ny = oy + dy;
-                        // check for any boxes in the way and store every position
-                        if let Some(o_ch) = world.get_isize(nx, ny) {
-                            match o_ch {
-                                ']' => {
-                                    if !boxes.contains(&(nx, ny)) {
-                                        selected.push((nx, ny));
-                                        boxes.push((nx, ny));
-                                    }
-                                    if !boxes.contains(&(nx - 1, ny)) {
-                                        selected.push((nx - 1, ny));
-                                        boxes.push((nx - 1, ny));
-                                    }
-                                }
-                                '[' => {
-                                    if !boxes.contains(&(nx, ny)) {
-                                        selected.push((nx, ny));
-                                        boxes.push((nx, ny));
-                                    }
-                                    if !boxes.contains(&(nx + 1, ny)) {
-                                        selected.push((nx + 1, ny));
-                                        boxes.push((nx + 1, ny));
-                                    }
-                                }
-                                '.' => {}
-                                '#' => {
-                                    break;
-                                }
-                                _ => panic!("character {} not recognised", o_ch),
-                            }
-                        } else {
-                            break;
-                        }
-                    }
-                    // in theory the last two boxes will be the furthest away, we can check if
-                    // there is a wall (if there is we do nothing!)
-                    let is_touching_wall = boxes
-                        .iter()
-                        .rev()
-                        .map(|(x, y)| (x + dx, y + dy))
-                        .any(|(lx, ly)| world.get_isize(lx, ly) == Some(&'#'));
-
-                    if !is_touching_wall {
-                        let new_boxes = boxes
-                            .iter()
-                            .filter(|(bx, by)| *world.get_unsafe(*bx as usize, *by as usize) == '[')
-                            .map(|(bx, by)| (bx + dx, by + dy))
-                            .collect::<Vec<_>>();
-                        for (bx, by) in boxes {
-                            *world.get_mut(bx, by).unwrap() = '.';
-                        }
-                        for (bx, by) in &new_boxes {
-                            *world.get_mut(*bx, *by).unwrap() = '[';
-                            *world.get_mut(bx + 1, *by).unwrap() = ']';
-                        }
-                        *world.get_mut(x, y).unwrap() = '.';
-                        *world.get_mut(new_x, new_y).unwrap() = '@';
-                        robot_pos = (new_x, new_y);
-                    }
-                }
-                '[' | ']' if dy == 0 => {
-                    let (mut cx, mut cy) = (new_x, new_y);
-                    let mut boxes = vec![];
-                    while let Some(other_ch) = world.get_isize(cx, cy) {
-                        match *other_ch {
-                            '[' | ']' => {
-                                boxes.push((cx, cy));
-                                cx += dx;
-                                cy += dy;
-                            }
-                            '.' => {
-                                // move all them boxes
-                                for (bx, by) in boxes.iter().rev() {
-                                    let old_box = *world.get_mut(*bx, *by).unwrap();
-                                    *world.get_mut(bx + dx, by + dy).unwrap() = old_box;
-                                    *world.get_mut(*bx, *by).unwrap() = '.';
-                                }
-                                *world.get_mut(x, y).unwrap() = '.';
-                                *world.get_mut(new_x, new_y).unwrap() = '@';
-                                robot_pos = (new_x, new_y);
-                                break;
-                            }
-                            '#' => {
-                                break;
-                            }
-                            _ => panic!("found unrecognised character {}", other_ch),
-                        }
-                    }
+
+    pub fn crates(&self) -> &[Crate] {
+        &self.crates
+    }
+
+    /// Every move made so far, oldest first.
+    pub fn log(&self) -> &[Step] {
+        &self.log
+    }
+
+    fn crate_at(&self, x: isize, y: isize) -> Option<usize> {
+        self.occupied.get_isize(x, y).copied().flatten()
+    }
+
+    fn is_wall(&self, x: isize, y: isize) -> bool {
+        self.walls.get_isize(x, y).is_none_or(|wall| *wall)
+    }
+
+    /// Every box that would be pushed if the robot moved, or `None` if something is in the way.
+    fn pushed_by(&self, direction: Direction) -> Option<Vec<usize>> {
+        let (dx, dy) = direction.vector();
+        let mut pushed: Vec<usize> = vec![];
+        let mut frontier = vec![(self.robot.0 + dx, self.robot.1 + dy)];
+        while let Some((x, y)) = frontier.pop() {
+            if self.is_wall(x, y) {
+                return None;
+            }
+            if let Some(index) = self.crate_at(x, y) {
+                if !pushed.contains(&index) {
+                    pushed.push(index);
+                    frontier.extend(
+                        self.crates[index]
+                            .cells()
+                            .map(|(cx, cy)| (cx + dx, cy + dy))
+                            .filter(|(nx, ny)| self.crate_at(*nx, *ny) != Some(index)),
+                    );
                 }
-                '.' => {
-                    *world.get_mut(x, y).unwrap() = '.';
-                    *world.get_mut(new_x, new_y).unwrap() = '@';
-                    robot_pos = (new_x, new_y);
+            }
+        }
+        Some(pushed)
+    }
+
+    fn shift(&mut self, pushed: &[usize], direction: Direction) {
+        let (dx, dy) = direction.vector();
+        for index in pushed {
+            for (x, y) in self.crates[*index].cells().collect::<Vec<_>>() {
+                self.occupied.update_unsafe(x as usize, y as usize, None);
+            }
+        }
+        for index in pushed {
+            let c = &mut self.crates[*index];
+            c.x += dx;
+            c.y += dy;
+            for (x, y) in self.crates[*index].cells().collect::<Vec<_>>() {
+                self.occupied
+                    .update_unsafe(x as usize, y as usize, Some(*index));
+            }
+        }
+    }
+
+    /// Tries to move the robot, pushing every box in the way all together or not at all.
+    pub fn step(&mut self, direction: Direction) -> &Step {
+        let step = match self.pushed_by(direction) {
+            Some(pushed) => {
+                let (dx, dy) = direction.vector();
+                self.shift(&pushed, direction);
+                self.robot = (self.robot.0 + dx, self.robot.1 + dy);
+                Step {
+                    direction,
+                    moved: true,
+                    pushed,
                 }
-                '#' => {}
-                _ => panic!("character not found {}", ch),
             }
+            None => Step {
+                direction,
+                moved: false,
+                pushed: vec![],
+            },
+        };
+        self.log.push(step);
+        self.log.last().unwrap()
+    }
+
+    /// Reverts the last move, returning it.
+    pub fn undo(&mut self) -> Option<Step> {
+        let step = self.log.pop()?;
+        if step.moved {
+            let (dx, dy) = step.direction.reverse().vector();
+            self.robot = (self.robot.0 + dx, self.robot.1 + dy);
+            self.shift(&step.pushed, step.direction.reverse());
         }
+        Some(step)
     }
 
-    let mut result = 0;
-    for x in 0..world.width {
-        for y in 0..world.height {
-            let ch = world.get_unsafe(x, y);
-            if *ch == '[' {
-                result += (y * 100) + x
+    /// Follows a move string, calling `inspect` after every step, e.g. to print the warehouse.
+    pub fn replay<F>(&mut self, moves: &str, mut inspect: F) -> Result<(), WarehouseError>
+    where
+        F: FnMut(&Warehouse),
+    {
+        for ch in moves.chars().filter(|ch| !ch.is_whitespace()) {
+            let direction = Direction::from_char(ch).ok_or(WarehouseError::InvalidMove(ch))?;
+            self.step(direction);
+            inspect(self);
+        }
+        Ok(())
+    }
+
+    /// Sum of every box's GPS coordinate, 100 times its distance from the top plus its distance
+    /// from the left.
+    pub fn gps(&self) -> isize {
+        self.crates.iter().map(|c| c.y * 100 + c.x).sum()
+    }
+}
+
+impl Display for Warehouse {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for y in 0..self.walls.height {
+            for x in 0..self.walls.width {
+                let ch = if *self.walls.get_unsafe(x, y) {
+                    '#'
+                } else if (x as isize, y as isize) == self.robot {
+                    '@'
+                } else if let Some(index) = self.occupied.get_unsafe(x, y) {
+                    let c = self.crates[*index];
+                    match (c.width, x as isize - c.x) {
+                        (1, _) => 'O',
+                        (_, 0) => '[',
+                        (width, offset) if offset == width - 1 => ']',
+                        _ => '=',
+                    }
+                } else {
+                    '.'
+                };
+                f.write_char(ch)?;
+            }
+            if y + 1 < self.walls.height {
+                f.write_char('\n')?;
             }
         }
+        Ok(())
     }
-    Some(result)
+}
+
+fn solve(input: &str, scale: usize) -> Option<usize> {
+    let (map, moves) = split_section(input);
+    let mut warehouse = Warehouse::parse(map, scale).ok()?;
+    warehouse.replay(moves, |_| {}).ok()?;
+    warehouse.gps().try_into().ok()
+}
+
+pub fn part_one(input: &str) -> Option<usize> {
+    solve(input, 1)
+}
+
+pub fn part_two(input: &str) -> Option<usize> {
+    solve(input, 2)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
-    // #[test]
-    // fn test_part_one() {
-    //     let result = part_one(&advent_of_code::template::read_file("examples", DAY));
-    //     assert_eq!(result, Some(10092));
-    // }
+    #[test]
+    fn test_part_one() {
+        let result = part_one(&advent_of_code::template::read_file("examples", DAY));
+        assert_eq!(result, Some(10092));
+    }
 
     #[test]
     fn test_part_two() {
         let result = part_two(&advent_of_code::template::read_file("examples", DAY));
         assert_eq!(result, Some(9021));
     }
+
+    #[test]
+    fn test_warehouse_undo_and_render() {
+        let map = "#######\n#...#.#\n#.....#\n#..OO@#\n#..O..#\n#.....#\n#######";
+        let mut warehouse = Warehouse::parse(map, 2).unwrap();
+        let start = warehouse.to_string();
+        assert_eq!(
+            start,
+            "##############\n##......##..##\n##..........##\n##....[][]@.##\n##....[]....##\n##..........##\n##############"
+        );
+        warehouse.replay("<vv<<^^", |_| {}).unwrap();
+        assert_eq!(warehouse.log().len(), 7);
+        assert_eq!(warehouse.log()[0].pushed.len(), 2);
+        let after = warehouse.to_string();
+        while warehouse.undo().is_some() {}
+        assert_eq!(warehouse.to_string(), start);
+        warehouse.replay("<vv<<^^", |_| {}).unwrap();
+        assert_eq!(warehouse.to_string(), after);
+        assert_eq!(
+            Warehouse::parse("#@[=#", 1).err(),
+            Some(WarehouseError::UnclosedBox((2, 0)))
+        );
+    }
+
+    #[test]
+    fn test_wide_boxes() {
+        let mut warehouse =
+            Warehouse::parse("#####\n#.@.#\n#.O.#\n#...#\n#...#\n#####", 3).unwrap();
+        assert_eq!(warehouse.crates()[0].width, 3);
+        warehouse.replay(">v", |_| {}).unwrap();
+        assert_eq!(warehouse.crates()[0].y, 3);
+        assert!(warehouse.step(Direction::Down).moved);
+        assert!(!warehouse.step(Direction::Down).moved);
+        assert_eq!(warehouse.crates()[0].y, 4);
+        assert_eq!(
+            Warehouse::parse("#@#", 0).err(),
+            Some(WarehouseError::ZeroScale)
+        );
+    }
 }
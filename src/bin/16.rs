@@ -1,329 +1,244 @@
-use std::{
-    collections::{HashMap, HashSet},
-    usize,
-};
+use std::{cmp::Reverse, collections::BinaryHeap, fmt::Display};
 
-use advent_of_code::XYWorld;
-use itertools::Itertools;
+use advent_of_code::{
+    grid::{GridError, Markers},
+    XYWorld,
+};
 
 advent_of_code::solution!(16);
 
-fn raycast(
-    world: &XYWorld<char>,
-    pos: (isize, isize),
-    direction: (isize, isize),
-) -> Option<(isize, isize)> {
-    let (mut x, mut y) = pos;
-    let (dx, dy) = direction;
-    let (rx, ry) = (dy, dx);
-    let (lx, ly) = (rx * -1, ry * -1);
-    while let Some(next) = world.get_isize(x, y) {
-        // this is a dead end what do we do?
-        if *next == 'E' {
-            break;
-        }
-        if let Some(left) = world.get_isize(x + lx, y + ly) {
-            if *left == '.' || *left == 'E' {
-                break;
-            }
-        }
-        if let Some(right) = world.get_isize(x + rx, y + ry) {
-            if *right == '.' || *right == 'E' {
-                break;
-            }
-        }
-        if *next != '.' {
-            return None;
-        }
-        x += dx;
-        y += dy;
-    }
-    Some((x, y))
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Facing {
+    North,
+    East,
+    South,
+    West,
 }
 
-fn raycast_all(
-    world: &XYWorld<char>,
-    pos: (isize, isize),
-    direction: (isize, isize),
-) -> Vec<(isize, isize)> {
-    let (mut x, mut y) = pos;
-    let (dx, dy) = direction;
-    let (rx, ry) = (dy, dx);
-    let (lx, ly) = (rx * -1, ry * -1);
-    let mut result = Vec::new();
-    while let Some(next) = world.get_isize(x, y) {
-        // this is a dead end what do we do?
-        if *next == 'E' {
-            result.push((x, y));
-            return result;
-        }
-        if world
-            .get_isize(x + rx, y + ry)
-            .is_some_and(|o| *o == '.' || *o == 'E')
-            || world
-                .get_isize(x + lx, y + ly)
-                .is_some_and(|o| *o == '.' || *o == 'E')
-        {
-            result.push((x, y))
-        }
-        if *next != '.' {
-            return result;
+impl Facing {
+    const ALL: [Facing; 4] = [Facing::North, Facing::East, Facing::South, Facing::West];
+
+    pub fn vector(&self) -> (isize, isize) {
+        match self {
+            Facing::North => (0, -1),
+            Facing::East => (1, 0),
+            Facing::South => (0, 1),
+            Facing::West => (-1, 0),
         }
-        x += dx;
-        y += dy;
     }
-    panic!()
+
+    pub fn clockwise(&self) -> Facing {
+        Facing::ALL[(*self as usize + 1) % 4]
+    }
+
+    pub fn counter_clockwise(&self) -> Facing {
+        Facing::ALL[(*self as usize + 3) % 4]
+    }
 }
 
-pub fn part_one(input: &str) -> Option<usize> {
-    let world = XYWorld::<char>::from_str::<char>(input);
-    let (mut cx, mut cy) = (0_isize, 0_isize);
-    let (mut gx, mut gy) = (0_isize, 0_isize);
-    for x in 0..world.width {
-        for y in 0..world.height {
-            if *world.get_unsafe(x, y) == 'S' {
-                (cx, cy) = (x as isize, y as isize);
-            }
-            if *world.get_unsafe(x, y) == 'E' {
-                (gx, gy) = (x as isize, y as isize);
-            }
-        }
+/// What it costs the reindeer to move one tile forward, and to turn 90 degrees in place.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Costs {
+    step: usize,
+    turn: usize,
+}
+
+impl Costs {
+    /// `None` unless both costs are positive, a free move would let the search loop forever.
+    pub fn new(step: usize, turn: usize) -> Option<Costs> {
+        (step > 0 && turn > 0).then_some(Costs { step, turn })
     }
-    // find a best guess via simple
-    let mut distances: HashMap<((isize, isize), (isize, isize)), usize> = HashMap::new();
-    let mut visited: HashSet<((isize, isize), (isize, isize))> = HashSet::new();
-    // unvisited nodes, the position with the direction it was found at;
-    let (mut dx, mut dy) = (1_isize, 0_isize);
-    // problem we don't know what angle, do we treat the direction we approach the node at as a new
-    // dimension for the node distances?
-    while let Some(next) = world.get_isize(cx + dx, cy + dy) {
-        // I guess we need to follow each line for as long as possible from the current x
-        let (rx, ry) = (dy, dx);
-        let (lx, ly) = (rx * -1, ry * -1);
-        if *next == '.' {
-            if let Some((nx, ny)) = raycast(&world, (cx + dx, cy + dy), (dx, dy)) {
-                let mut score = *distances.get(&((cx, cy), (dx, dy))).unwrap_or(&0);
-                score += nx.abs_diff(cx) + ny.abs_diff(cy);
-                *distances.entry(((nx, ny), (dx, dy))).or_insert(0) = score;
-            }
-        }
-        if let Some(right) = world.get_isize(cx + rx, cy + ry) {
-            // we're at a left node
-            if *right == '.' {
-                let mut score = *distances.get(&((cx, cy), (dx, dy))).unwrap_or(&0);
-                score += 1000;
-                if let Some((nx, ny)) = raycast(&world, (cx + rx, cy + ry), (rx, ry)) {
-                    score += nx.abs_diff(cx) + ny.abs_diff(cy);
-                    *distances.entry(((nx, ny), (rx, ry))).or_insert(0) = score;
-                }
-            }
-        }
-        if let Some(left) = world.get_isize(cx + lx, cy + ly) {
-            if *left == '.' {
-                let mut score = *distances.get(&((cx, cy), (dx, dy))).unwrap_or(&0);
-                // we had to turn so we add the score to these paths
-                score += 1000;
-                if let Some((nx, ny)) = raycast(&world, (cx + lx, cy + ly), (lx, ly)) {
-                    score += nx.abs_diff(cx) + ny.abs_diff(cy);
-                    *distances.entry(((nx, ny), (lx, ly))).or_insert(0) = score;
-                }
-            }
-        }
-        visited.insert(((cx, cy), (dx, dy)));
-        if let Some((pos, _)) = distances
-            .iter()
-            .filter(|(other, _)| !visited.contains(other))
-            .sorted_by(|(_, value_one), (_, value_two)| value_one.cmp(value_two))
-            .next()
-        {
-            ((cx, cy), (dx, dy)) = *pos;
-        } else {
-            break;
-        }
+
+    pub fn step(&self) -> usize {
+        self.step
     }
 
-    distances
-        .iter()
-        .sorted_by(|(_, value_one), (_, value_two)| value_one.cmp(value_two))
-        .find_map(|((pos, _), value)| if *pos == (gx, gy) { Some(value) } else { None })
-        .copied()
+    pub fn turn(&self) -> usize {
+        self.turn
+    }
 }
 
-pub fn part_two(input: &str) -> Option<usize> {
-    let world = XYWorld::<char>::from_str::<char>(input);
-    let (mut cx, mut cy) = (0_isize, 0_isize);
-    let (mut gx, mut gy) = (0_isize, 0_isize);
-    for x in 0..world.width {
-        for y in 0..world.height {
-            if *world.get_unsafe(x, y) == 'S' {
-                (cx, cy) = (x as isize, y as isize);
-            }
-            if *world.get_unsafe(x, y) == 'E' {
-                (gx, gy) = (x as isize, y as isize);
-            }
+impl Default for Costs {
+    fn default() -> Self {
+        Costs {
+            step: 1,
+            turn: 1000,
         }
     }
-    // find a best guess via simple
-    let mut distances: HashMap<((isize, isize), (isize, isize)), usize> = HashMap::new();
-    let mut connected: HashMap<(isize, isize, isize, isize), HashSet<(isize, isize, usize)>> =
-        HashMap::new();
-    let mut visited: HashSet<((isize, isize), (isize, isize))> = HashSet::new();
-    let (mut dx, mut dy) = (1_isize, 0_isize);
-    while let Some(next) = world.get_isize(cx + dx, cy + dy) {
-        // I guess we need to follow each line for as long as possible from the current x
-        let (rx, ry) = (dy, dx);
-        let (lx, ly) = (rx * -1, ry * -1);
-        if *next == '.' {
-            let important_points = raycast_all(&world, (cx + dx, cy + dy), (dx, dy));
-            for (nx, ny) in &important_points {
-                connected
-                    .entry((*nx, *ny, dx, dy))
-                    .and_modify(|connected| {
-                        connected.insert((cx, cy, nx.abs_diff(cx) + ny.abs_diff(cy)));
-                    })
-                    .or_insert(HashSet::from([(cx, cy, nx.abs_diff(cx) + ny.abs_diff(cy))]));
-            }
-            if let Some((nx, ny)) = important_points.first() {
-                let mut score = *distances.get(&((cx, cy), (dx, dy))).unwrap_or(&0);
-                score += nx.abs_diff(cx) + ny.abs_diff(cy);
-                let old_score = distances
-                    .get(&((*nx, *ny), (dx, dy)))
-                    .unwrap_or(&usize::MAX);
-                *distances.entry(((*nx, *ny), (dx, dy))).or_insert(0) = score.min(*old_score);
-            }
-        }
-        if let Some(right) = world.get_isize(cx + rx, cy + ry) {
-            if *right == '.' {
-                let important_points = raycast_all(&world, (cx + rx, cy + ry), (rx, ry));
-                for (nx, ny) in &important_points {
-                    connected
-                        .entry((*nx, *ny, rx, ry))
-                        .and_modify(|connected| {
-                            connected.insert((cx, cy, 1000 + nx.abs_diff(cx) + ny.abs_diff(cy)));
-                        })
-                        .or_insert(HashSet::from([(
-                            cx,
-                            cy,
-                            1000 + nx.abs_diff(cx) + ny.abs_diff(cy),
-                        )]));
-                }
-                if let Some((nx, ny)) = important_points.first() {
-                    let mut score = *distances.get(&((cx, cy), (dx, dy))).unwrap_or(&0);
-                    score += 1000;
-                    score += nx.abs_diff(cx) + ny.abs_diff(cy);
-                    let old_score = distances
-                        .get(&((*nx, *ny), (rx, ry)))
-                        .unwrap_or(&usize::MAX);
-                    *distances.entry(((*nx, *ny), (rx, ry))).or_insert(0) = score.min(*old_score);
-                }
+}
+
+pub struct Maze {
+    walls: XYWorld<bool>,
+    start: (usize, usize),
+    end: (usize, usize),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Solution {
+    pub cost: usize,
+    /// Number of distinct optimal paths, a path that turns differently counts separately.
+    pub paths: u128,
+    /// Every tile on at least one optimal path.
+    pub tiles: Vec<(usize, usize)>,
+}
+
+type State = ((usize, usize), Facing);
+
+impl Maze {
+    pub fn parse(input: &str) -> Result<Maze, GridError> {
+        let (walls, markers): (XYWorld<bool>, Markers) =
+            XYWorld::parse_with_markers(input, &[('S', false), ('E', false)], |ch| match ch {
+                '#' => Some(true),
+                '.' => Some(false),
+                _ => None,
+            })?;
+        let start = markers.require('S')?;
+        let end = markers.require('E')?;
+        Ok(Maze { walls, start, end })
+    }
+
+    fn index(&self, ((x, y), facing): State) -> usize {
+        (y * self.walls.width + x) * 4 + facing as usize
+    }
+
+    fn is_open(&self, x: isize, y: isize) -> bool {
+        self.walls.get_isize(x, y).is_some_and(|wall| !wall)
+    }
+
+    fn ahead(&self, ((x, y), facing): State, sign: isize) -> Option<(usize, usize)> {
+        let (dx, dy) = facing.vector();
+        let (nx, ny) = (x as isize + dx * sign, y as isize + dy * sign);
+        self.is_open(nx, ny).then_some((nx as usize, ny as usize))
+    }
+
+    /// States reachable from `state` in one move, with the cost of the move.
+    fn successors(&self, state: State, costs: Costs) -> impl Iterator<Item = (State, usize)> {
+        let (pos, facing) = state;
+        let forward = self
+            .ahead(state, 1)
+            .map(|next| ((next, facing), costs.step));
+        [
+            ((pos, facing.clockwise()), costs.turn),
+            ((pos, facing.counter_clockwise()), costs.turn),
+        ]
+        .into_iter()
+        .chain(forward)
+    }
+
+    /// States that reach `state` in one move, with the cost of the move.
+    fn predecessors(&self, state: State, costs: Costs) -> impl Iterator<Item = (State, usize)> {
+        let (pos, facing) = state;
+        let backward = self
+            .ahead(state, -1)
+            .map(|previous| ((previous, facing), costs.step));
+        [
+            ((pos, facing.clockwise()), costs.turn),
+            ((pos, facing.counter_clockwise()), costs.turn),
+        ]
+        .into_iter()
+        .chain(backward)
+    }
+
+    /// Dijkstra over `(position, facing)` starting east on `S`, counting the optimal paths into each
+    /// state as they are settled.
+    pub fn solve(&self, costs: Costs) -> Option<Solution> {
+        let states = self.walls.width * self.walls.height * 4;
+        let mut distances = vec![usize::MAX; states];
+        let mut counts = vec![0_u128; states];
+        let mut queue = BinaryHeap::new();
+        let start = (self.start, Facing::East);
+        distances[self.index(start)] = 0;
+        counts[self.index(start)] = 1;
+        queue.push(Reverse((0, self.index(start), start)));
+        while let Some(Reverse((distance, index, state))) = queue.pop() {
+            if distance > distances[index] {
+                continue;
             }
-        }
-        if let Some(left) = world.get_isize(cx + lx, cy + ly) {
-            if *left == '.' {
-                let important_points = raycast_all(&world, (cx + lx, cy + ly), (lx, ly));
-                for (nx, ny) in &important_points {
-                    connected
-                        .entry((*nx, *ny, lx, ly))
-                        .and_modify(|connected| {
-                            connected.insert((cx, cy, 1000 + nx.abs_diff(cx) + ny.abs_diff(cy)));
-                        })
-                        .or_insert(HashSet::from([(
-                            cx,
-                            cy,
-                            1000 + nx.abs_diff(cx) + ny.abs_diff(cy),
-                        )]));
-                }
-                if let Some((nx, ny)) = important_points.first() {
-                    let mut score = *distances.get(&((cx, cy), (dx, dy))).unwrap_or(&0);
-                    score += 1000;
-                    score += nx.abs_diff(cx) + ny.abs_diff(cy);
-                    let old_score = distances
-                        .get(&((*nx, *ny), (lx, ly)))
-                        .unwrap_or(&usize::MAX);
-                    *distances.entry(((*nx, *ny), (lx, ly))).or_insert(0) = score.min(*old_score);
+            for (next, cost) in self.successors(state, costs) {
+                // A path that overflows can never be the cheapest.
+                let Some(next_distance) = distance.checked_add(cost) else {
+                    continue;
+                };
+                let next_index = self.index(next);
+                if next_distance < distances[next_index] {
+                    distances[next_index] = next_distance;
+                    counts[next_index] = counts[index];
+                    queue.push(Reverse((next_distance, next_index, next)));
+                } else if next_distance == distances[next_index] {
+                    counts[next_index] = counts[next_index].saturating_add(counts[index]);
                 }
             }
         }
-        visited.insert(((cx, cy), (dx, dy)));
-        if let Some((pos, _)) = distances
+
+        let ends = Facing::ALL.map(|facing| (self.end, facing));
+        let cost = ends
             .iter()
-            .filter(|(other, _)| !visited.contains(other))
-            .sorted_by(|(_, value_one), (_, value_two)| value_one.cmp(value_two))
-            .next()
-        {
-            ((cx, cy), (dx, dy)) = *pos;
-        } else {
-            break;
+            .map(|end| distances[self.index(*end)])
+            .min()
+            .filter(|cost| *cost != usize::MAX)?;
+        let mut optimal: Vec<State> = ends
+            .into_iter()
+            .filter(|end| distances[self.index(*end)] == cost)
+            .collect();
+        let paths = optimal
+            .iter()
+            .map(|end| counts[self.index(*end)])
+            .fold(0_u128, u128::saturating_add);
+
+        let mut on_path = vec![false; states];
+        let mut tiles = XYWorld::filled(self.walls.width, self.walls.height, false);
+        for end in &optimal {
+            on_path[self.index(*end)] = true;
         }
-    }
-    let shortest_distance = distances
-        .iter()
-        .sorted_by(|(_, value_one), (_, value_two)| value_one.cmp(value_two))
-        .find_map(|((pos, _), value)| if *pos == (gx, gy) { Some(value) } else { None })
-        .copied()
-        .unwrap();
-
-    let mut points = distances
-        .iter()
-        .sorted_by(|(_, value_one), (_, value_two)| value_one.cmp(value_two))
-        .filter_map(|((pos, dir), score)| {
-            if *pos == (gx, gy) && *score == shortest_distance {
-                Some((gx, gy, dir.0, dir.1))
-            } else {
-                None
-            }
-        })
-        .collect_vec();
-    let mut seen: HashSet<(isize, isize, isize, isize)> = HashSet::new();
-    let mut path: XYWorld<char> = XYWorld::<char>::blank(world.width, world.height);
-    while let Some((x, y, dx, dy)) = points.pop() {
-        let direction = (dx, dy);
-        if let Some(distance) = distances.get(&((x, y), direction)) {
-            if let Some(connections) = connected.get(&(x, y, dx, dy)) {
-                for (cx, cy, weight) in connections {
-                    let right_dir = (dy, dx);
-                    let left_dir = (-dy, -dx);
-                    for direction in [direction, right_dir, left_dir] {
-                        if let Some(other_distance) = distances.get(&((*cx, *cy), direction)) {
-                            if (*distance as isize - *weight as isize) == *other_distance as isize {
-                                path.draw_between(
-                                    (x as usize, y as usize),
-                                    (*cx as usize, *cy as usize),
-                                    'O',
-                                );
-                                if seen.insert((*cx, *cy, direction.0, direction.1)) {
-                                    points.push((*cx, *cy, direction.0, direction.1));
-                                }
-                            } else if (*distance as isize - *weight as isize) == 0 {
-                                path.draw_between(
-                                    (x as usize, y as usize),
-                                    (*cx as usize, *cy as usize),
-                                    'O',
-                                );
-                            }
-                        } else {
-                            if (*distance as isize - *weight as isize) == 0 {
-                                path.draw_between(
-                                    (x as usize, y as usize),
-                                    (*cx as usize, *cy as usize),
-                                    'O',
-                                );
-                            }
-                        };
-                    }
+        while let Some(state) = optimal.pop() {
+            let ((x, y), _) = state;
+            tiles.update_unsafe(x, y, true);
+            let distance = distances[self.index(state)];
+            for (previous, cost) in self.predecessors(state, costs) {
+                let index = self.index(previous);
+                if distances[index].checked_add(cost) == Some(distance) && !on_path[index] {
+                    on_path[index] = true;
+                    optimal.push(previous);
                 }
             }
         }
+        let tiles = (0..self.walls.height)
+            .flat_map(|y| (0..self.walls.width).map(move |x| (x, y)))
+            .filter(|(x, y)| *tiles.get_unsafe(*x, *y))
+            .collect();
+        Some(Solution { cost, paths, tiles })
     }
-    let mut result = 0;
-    for x in 0..world.width {
-        for y in 0..world.height {
-            if *path.get_unsafe(x, y) == 'O' {
-                result += 1;
-            }
-        }
+
+    /// The maze with every tile of `solution` drawn as `O`.
+    pub fn overlay(&self, solution: &Solution) -> XYWorld<char> {
+        let mut world = self.walls.map(|wall| if *wall { '#' } else { '.' });
+        world.draw_points(solution.tiles.clone(), 'O');
+        world.update_unsafe(self.start.0, self.start.1, 'S');
+        world.update_unsafe(self.end.0, self.end.1, 'E');
+        world
     }
-    Some(result)
+}
+
+impl Display for Maze {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let world = self.overlay(&Solution {
+            cost: 0,
+            paths: 0,
+            tiles: vec![],
+        });
+        write!(f, "{}", world)
+    }
+}
+
+pub fn part_one(input: &str) -> Option<usize> {
+    let maze = Maze::parse(input).ok()?;
+    maze.solve(Costs::default()).map(|solution| solution.cost)
+}
+
+pub fn part_two(input: &str) -> Option<usize> {
+    let maze = Maze::parse(input).ok()?;
+    maze.solve(Costs::default())
+        .map(|solution| solution.tiles.len())
 }
 
 #[cfg(test)]
@@ -341,4 +256,57 @@ mod tests {
         let result = part_two(&advent_of_code::template::read_file("examples", DAY));
         assert_eq!(result, Some(45));
     }
+
+    const SECOND_EXAMPLE: &str = "#################
+#...#...#...#..E#
+#.#.#.#.#.#.#.#.#
+#.#.#.#...#...#.#
+#.#.#.#.###.#.#.#
+#...#.#.#.....#.#
+#.#.#.#.#.#####.#
+#.#...#.#.#.....#
+#.#.#####.#.###.#
+#.#.#.......#...#
+#.#.###.#####.###
+#.#.#...#.....#.#
+#.#.#.#####.###.#
+#.#.#.........#.#
+#.#.#.#########.#
+#S#.............#
+#################";
+
+    #[test]
+    fn test_second_example() {
+        assert_eq!(part_one(SECOND_EXAMPLE), Some(11048));
+        assert_eq!(part_two(SECOND_EXAMPLE), Some(64));
+    }
+
+    #[test]
+    fn test_path_counts_and_overlay() {
+        let maze = Maze::parse("#######\n#.....#\n#S###E#\n#.....#\n#######").unwrap();
+        let solution = maze.solve(Costs::default()).unwrap();
+        assert_eq!(solution.cost, 3006);
+        assert_eq!(solution.paths, 2);
+        assert_eq!(solution.tiles.len(), 12);
+        assert_eq!(
+            maze.overlay(&solution).to_string(),
+            "#######\n#OOOOO#\n#S###E#\n#OOOOO#\n#######"
+        );
+
+        assert_eq!(Costs::new(0, 1), None);
+        assert_eq!(Costs::new(1, 0), None);
+        let expensive = Costs::new(usize::MAX, 1).unwrap();
+        assert_eq!(maze.solve(expensive), None);
+        let cheap_turns = Costs::new(1, 1).unwrap();
+        let solution = maze.solve(cheap_turns).unwrap();
+        assert_eq!(solution.cost, 9);
+        assert_eq!(
+            Maze::parse("#.#\n#E#").err(),
+            Some(GridError::MissingMarker('S'))
+        );
+        assert!(Maze::parse("#S#\n###\n#E#")
+            .unwrap()
+            .solve(cheap_turns)
+            .is_none());
+    }
 }
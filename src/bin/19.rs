@@ -1,94 +1,189 @@
-use std::collections::{BinaryHeap, HashMap, HashSet, VecDeque};
-
-use itertools::Itertools;
+use advent_of_code::parse::{sections, ParseError};
 
 advent_of_code::solution!(19);
 
-pub fn part_one(input: &str) -> Option<u32> {
-    let mut lines = input.lines();
-    let patterns = lines.next().unwrap().split(", ").sorted().collect_vec();
-    assert!(lines.next().is_some_and(|line| line.is_empty()));
-    let mut successes = 0;
-    for line in lines {
-        let mut ranges: HashMap<usize, Vec<usize>> = HashMap::new();
-        for pattern in &patterns {
-            let mut idx = 0;
-            for chunk in line.chars().collect_vec().windows(pattern.len()) {
-                let str = chunk.iter().collect::<String>();
-                if &str == pattern {
-                    ranges
-                        .entry(idx)
-                        .and_modify(|m| m.push(idx + pattern.len()))
-                        .or_insert(vec![idx + pattern.len()]);
-                }
-                idx += 1;
-            }
+#[derive(Debug, Default)]
+struct Node {
+    children: Vec<(u8, usize)>,
+    /// Index of the pattern that ends at this node.
+    pattern: Option<usize>,
+}
+
+/// A trie over the towel patterns, built once and shared by every design.
+#[derive(Debug)]
+pub struct Towels {
+    patterns: Vec<String>,
+    nodes: Vec<Node>,
+}
+
+impl Towels {
+    pub fn new<S: Into<String>>(patterns: impl IntoIterator<Item = S>) -> Towels {
+        let mut towels = Towels {
+            patterns: vec![],
+            nodes: vec![Node::default()],
+        };
+        for pattern in patterns {
+            towels.insert(pattern.into());
         }
-        // If no range starts at 0 we've done nothing
-        let mut seen: HashSet<usize> = HashSet::new();
-        if let Some(next_up) = ranges.get(&0) {
-            let mut next_up = next_up.into_iter().collect::<BinaryHeap<_>>();
-            while let Some(start_at) = next_up.pop() {
-                if seen.insert(*start_at) {
-                    if *start_at == line.len() {
-                        successes += 1;
-                        next_up.clear();
-                        break;
-                    }
-                    if let Some(range) = ranges.get(&start_at) {
-                        for next in range {
-                            next_up.push(next);
-                        }
-                    }
+        towels
+    }
+
+    fn insert(&mut self, pattern: String) {
+        let mut node = 0;
+        for byte in pattern.bytes() {
+            node = match self.child(node, byte) {
+                Some(next) => next,
+                None => {
+                    self.nodes.push(Node::default());
+                    let next = self.nodes.len() - 1;
+                    self.nodes[node].children.push((byte, next));
+                    next
                 }
-            }
+            };
+        }
+        if node != 0 && self.nodes[node].pattern.is_none() {
+            self.nodes[node].pattern = Some(self.patterns.len());
         }
+        self.patterns.push(pattern);
     }
-    Some(successes)
-}
 
-pub fn part_two(input: &str) -> Option<usize> {
-    let mut lines = input.lines();
-    let patterns = lines.next().unwrap().split(", ").sorted().collect_vec();
-    assert!(lines.next().is_some_and(|line| line.is_empty()));
-    let mut successes = 0;
-    for line in lines {
-        let mut tree: HashMap<usize, VecDeque<usize>> = HashMap::new();
-        for pattern in &patterns {
-            let mut idx = 0;
-            for chunk in line.chars().collect_vec().windows(pattern.len()) {
-                let str = chunk.iter().collect::<String>();
-                if &str == pattern {
-                    tree.entry(idx + pattern.len())
-                        .and_modify(|m| {
-                            m.push_back(idx);
-                        })
-                        .or_insert(VecDeque::from_iter(vec![idx]));
-                }
-                idx += 1;
-            }
+    fn child(&self, node: usize, byte: u8) -> Option<usize> {
+        self.nodes[node]
+            .children
+            .iter()
+            .find(|(edge, _)| *edge == byte)
+            .map(|(_, next)| *next)
+    }
+
+    /// Parses the pattern line and the designs after the blank line.
+    pub fn parse(input: &str) -> Result<(Towels, Vec<&str>), ParseError> {
+        let sections = sections(input);
+        let patterns: Vec<String> = sections
+            .first()
+            .map_or(Ok(vec![]), |section| section.line(0)?.separated(","))?;
+        let designs = sections.get(1).map_or(vec![], |section| {
+            section.lines().iter().map(|line| line.text).collect()
+        });
+        Ok((Towels::new(patterns), designs))
+    }
+
+    pub fn pattern(&self, index: usize) -> &str {
+        &self.patterns[index]
+    }
+
+    /// Every pattern that matches the design at `start`, as `(pattern, length)`.
+    pub fn matches_at<'a>(
+        &'a self,
+        design: &'a [u8],
+        start: usize,
+    ) -> impl Iterator<Item = (usize, usize)> + 'a {
+        design[start..]
+            .iter()
+            .scan(0, move |node, byte| {
+                *node = self.child(*node, *byte)?;
+                Some(*node)
+            })
+            .enumerate()
+            .filter_map(|(offset, node)| {
+                self.nodes[node]
+                    .pattern
+                    .map(|pattern| (pattern, offset + 1))
+            })
+    }
+
+    /// `ways[i]` is the number of arrangements of the design from byte `i` on, saturating at
+    /// `u128::MAX`. Each position only walks the trie as deep as the longest pattern.
+    fn ways(&self, design: &[u8]) -> Vec<u128> {
+        let mut ways = vec![0_u128; design.len() + 1];
+        ways[design.len()] = 1;
+        for start in (0..design.len()).rev() {
+            ways[start] = self
+                .matches_at(design, start)
+                .fold(0, |total, (_, len)| total.saturating_add(ways[start + len]));
         }
+        ways
+    }
 
-        let mut strengths: HashMap<usize, usize> = HashMap::new();
-        // By using a binary heap we can _almost_ gaurentee we've visited every parent node
-        // **BEFORE** we visit a child, neat!
-        let mut next_up = BinaryHeap::from_iter(vec![line.len()]);
-        while let Some(current) = next_up.pop() {
-            let curr_strength = strengths.get(&current).unwrap_or(&1).clone();
-            if let Some(range) = tree.get(&current) {
-                for value in range {
-                    if let Some(freq) = strengths.get_mut(value) {
-                        *freq += curr_strength;
-                    } else {
-                        strengths.insert(*value, curr_strength);
-                        next_up.push(*value);
-                    }
-                }
+    pub fn count(&self, design: &str) -> u128 {
+        self.ways(design.as_bytes())[0]
+    }
+
+    pub fn is_possible(&self, design: &str) -> bool {
+        self.count(design) > 0
+    }
+
+    /// Every arrangement of the design, produced one at a time and only down branches known to
+    /// finish, so taking the first few is cheap even when there are trillions.
+    pub fn arrangements<'a>(&'a self, design: &'a str) -> Arrangements<'a> {
+        let ways = self.ways(design.as_bytes());
+        let stack = if ways[0] > 0 {
+            vec![(0, vec![])]
+        } else {
+            vec![]
+        };
+        Arrangements {
+            towels: self,
+            design: design.as_bytes(),
+            ways,
+            stack,
+        }
+    }
+}
+
+pub struct Arrangements<'a> {
+    towels: &'a Towels,
+    design: &'a [u8],
+    ways: Vec<u128>,
+    stack: Vec<(usize, Vec<usize>)>,
+}
+
+impl<'a> Iterator for Arrangements<'a> {
+    type Item = Vec<&'a str>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some((position, used)) = self.stack.pop() {
+            if position == self.design.len() {
+                return Some(
+                    used.iter()
+                        .map(|pattern| self.towels.pattern(*pattern))
+                        .collect(),
+                );
+            }
+            let mut next: Vec<(usize, usize)> = self
+                .towels
+                .matches_at(self.design, position)
+                .filter(|(_, len)| self.ways[position + len] > 0)
+                .collect();
+            // Pushed in reverse so shorter first towels come out first.
+            next.reverse();
+            for (pattern, len) in next {
+                let mut used = used.clone();
+                used.push(pattern);
+                self.stack.push((position + len, used));
             }
         }
-        successes += strengths.get(&0).unwrap_or(&0);
+        None
     }
-    Some(successes)
+}
+
+pub fn part_one(input: &str) -> Option<usize> {
+    let (towels, designs) = Towels::parse(input).ok()?;
+    Some(
+        designs
+            .iter()
+            .filter(|design| towels.is_possible(design))
+            .count(),
+    )
+}
+
+pub fn part_two(input: &str) -> Option<u128> {
+    let (towels, designs) = Towels::parse(input).ok()?;
+    Some(
+        designs
+            .iter()
+            .map(|design| towels.count(design))
+            .fold(0_u128, u128::saturating_add),
+    )
 }
 
 #[cfg(test)]
@@ -106,4 +201,35 @@ mod tests {
         let result = part_two(&advent_of_code::template::read_file("examples", DAY));
         assert_eq!(result, Some(16));
     }
+
+    #[test]
+    fn test_arrangements() {
+        let (towels, _) =
+            Towels::parse(&advent_of_code::template::read_file("examples", DAY)).unwrap();
+        assert_eq!(towels.count("gbbr"), 4);
+        assert_eq!(
+            towels.arrangements("gbbr").collect::<Vec<_>>(),
+            vec![
+                vec!["g", "b", "b", "r"],
+                vec!["g", "b", "br"],
+                vec!["gb", "b", "r"],
+                vec!["gb", "br"],
+            ]
+        );
+        assert!(!towels.is_possible("ubwu"));
+        assert_eq!(towels.arrangements("ubwu").next(), None);
+
+        let towels = Towels::new(["a", "aa"]);
+        let design = "a".repeat(150);
+        // Fibonacci numbers, well past what a u64 holds.
+        assert_eq!(towels.count(&design), 16130531424904581415797907386349);
+        assert_eq!(towels.count(&"a".repeat(200)), u128::MAX);
+        assert_eq!(
+            towels
+                .arrangements(&design)
+                .next()
+                .map(|towels| towels.len()),
+            Some(150)
+        );
+    }
 }
@@ -0,0 +1,14 @@
+mod parse;
+mod pattern;
+mod regions;
+mod search;
+mod sparse;
+mod transform;
+mod view;
+
+pub use parse::*;
+pub use pattern::*;
+pub use regions::*;
+pub use search::*;
+pub use sparse::*;
+pub use view::*;
@@ -0,0 +1,220 @@
+use std::{collections::HashMap, fmt::Display, str::FromStr};
+
+use crate::{BinaryGrid, XYWorld};
+
+/// Why a grid could not be parsed, lines and columns count from 1.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GridError {
+    Empty,
+    Ragged {
+        line: usize,
+        expected: usize,
+        found: usize,
+    },
+    InvalidCell {
+        line: usize,
+        column: usize,
+        ch: char,
+    },
+    /// A marker the caller needs never appeared in the grid.
+    MissingMarker(char),
+}
+
+impl Display for GridError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            GridError::Empty => write!(f, "grid has no rows"),
+            GridError::Ragged {
+                line,
+                expected,
+                found,
+            } => write!(
+                f,
+                "line {line} is {found} cells wide, expected {expected} like the first row"
+            ),
+            GridError::InvalidCell { line, column, ch } => {
+                write!(f, "unexpected {ch:?} at line {line}, column {column}")
+            }
+            GridError::MissingMarker(ch) => write!(f, "no {ch:?} marker in the grid"),
+        }
+    }
+}
+
+impl std::error::Error for GridError {}
+
+/// Positions of the marker characters pulled out of a grid while parsing.
+#[derive(Debug, Clone, Default)]
+pub struct Markers {
+    positions: HashMap<char, Vec<(usize, usize)>>,
+}
+
+impl Markers {
+    /// The first position of `marker` in reading order.
+    pub fn first(&self, marker: char) -> Option<(usize, usize)> {
+        self.all(marker).first().copied()
+    }
+
+    /// Like [`Markers::first`], but a missing marker is an error.
+    pub fn require(&self, marker: char) -> Result<(usize, usize), GridError> {
+        self.first(marker).ok_or(GridError::MissingMarker(marker))
+    }
+
+    pub fn all(&self, marker: char) -> &[(usize, usize)] {
+        self.positions.get(&marker).map_or(&[], |v| v.as_slice())
+    }
+}
+
+/// Splits off the leading block of non-blank lines, returning it and whatever follows the blank
+/// line after it. Handy for inputs with a grid followed by other sections.
+pub fn split_section(input: &str) -> (&str, &str) {
+    let mut offset = 0;
+    for line in input.split_inclusive('\n') {
+        if line.trim_end_matches(['\r', '\n']).is_empty() {
+            return (&input[..offset], &input[offset + line.len()..]);
+        }
+        offset += line.len();
+    }
+    (input, "")
+}
+
+fn parse_rows<B, F>(str: &str, mut f: F) -> Result<(Vec<Vec<B>>, usize), GridError>
+where
+    F: FnMut(usize, usize, char) -> Option<B>,
+{
+    let mut world: Vec<Vec<B>> = vec![];
+    for (y, line) in str.lines().enumerate() {
+        let row = line
+            .chars()
+            .enumerate()
+            .map(|(x, ch)| {
+                f(x, y, ch).ok_or(GridError::InvalidCell {
+                    line: y + 1,
+                    column: x + 1,
+                    ch,
+                })
+            })
+            .collect::<Result<Vec<B>, GridError>>()?;
+        if let Some(first) = world.first() {
+            if first.len() != row.len() {
+                return Err(GridError::Ragged {
+                    line: y + 1,
+                    expected: first.len(),
+                    found: row.len(),
+                });
+            }
+        }
+        world.push(row);
+    }
+    let width = world.first().map(|row| row.len()).ok_or(GridError::Empty)?;
+    Ok((world, width))
+}
+
+impl<A> XYWorld<A> {
+    /// Parses every character with `A::from_str`, failing on the first cell that doesn't parse or
+    /// the first row that differs in width.
+    pub fn parse(str: &str) -> Result<XYWorld<A>, GridError>
+    where
+        A: FromStr,
+    {
+        XYWorld::parse_with(str, |ch| {
+            let mut buf = [0; 4];
+            ch.encode_utf8(&mut buf).parse().ok()
+        })
+    }
+
+    /// Parses every character through `f`, a `None` is reported as an invalid cell.
+    pub fn parse_with<F>(str: &str, f: F) -> Result<XYWorld<A>, GridError>
+    where
+        F: Fn(char) -> Option<A>,
+    {
+        let (world, width) = parse_rows(str, |_, _, ch| f(ch))?;
+        let height = world.len();
+        Ok(XYWorld {
+            world,
+            height,
+            width,
+        })
+    }
+
+    /// Like `parse_with`, but each `(marker, replacement)` character has its positions recorded
+    /// and is stored as `replacement` instead of going through `f`.
+    pub fn parse_with_markers<F>(
+        str: &str,
+        markers: &[(char, A)],
+        f: F,
+    ) -> Result<(XYWorld<A>, Markers), GridError>
+    where
+        A: Clone,
+        F: Fn(char) -> Option<A>,
+    {
+        let mut found = Markers::default();
+        let (world, width) = parse_rows(str, |x, y, ch| {
+            match markers.iter().find(|(marker, _)| *marker == ch) {
+                Some((marker, replacement)) => {
+                    found.positions.entry(*marker).or_default().push((x, y));
+                    Some(replacement.clone())
+                }
+                None => f(ch),
+            }
+        })?;
+        let height = world.len();
+        Ok((
+            XYWorld {
+                world,
+                height,
+                width,
+            },
+            found,
+        ))
+    }
+}
+
+impl BinaryGrid {
+    /// Parses a grid where `f` decides which characters are set, rejecting ragged rows.
+    pub fn parse<F>(str: &str, f: F) -> Result<BinaryGrid, GridError>
+    where
+        F: Fn(char) -> bool,
+    {
+        let (world, width) = parse_rows(str, |_, _, ch| Some(f(ch)))?;
+        let height = world.len();
+        Ok(BinaryGrid {
+            world,
+            width,
+            height,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_errors() {
+        assert_eq!(
+            XYWorld::<u8>::parse("12\n3x").unwrap_err(),
+            GridError::InvalidCell {
+                line: 2,
+                column: 2,
+                ch: 'x'
+            }
+        );
+        assert_eq!(
+            XYWorld::<char>::parse("##\n#\n##").unwrap_err(),
+            GridError::Ragged {
+                line: 2,
+                expected: 2,
+                found: 1
+            }
+        );
+        assert_eq!(XYWorld::<char>::parse("").unwrap_err(), GridError::Empty);
+        let (map, rest) = split_section("#@\n.S\n\n<>\n");
+        assert_eq!(rest, "<>\n");
+        let (world, markers) =
+            XYWorld::parse_with_markers(map, &[('@', '.'), ('S', '.')], Some).unwrap();
+        assert_eq!(world.to_string(), "#.\n..");
+        assert_eq!(markers.first('@'), Some((1, 0)));
+        assert_eq!(markers.all('S'), &[(1, 1)]);
+        assert_eq!(markers.require('E'), Err(GridError::MissingMarker('E')));
+    }
+}
@@ -0,0 +1,194 @@
+use crate::XYWorld;
+
+use super::XYView;
+
+/// A rectangular template to search for in an `XYWorld`, `None` cells match anything.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Pattern<A> {
+    cells: Vec<Vec<Option<A>>>,
+    pub width: usize,
+    pub height: usize,
+}
+
+impl<A> Pattern<A> {
+    pub fn new(cells: Vec<Vec<Option<A>>>) -> Pattern<A> {
+        let height = cells.len();
+        let width = cells.first().map_or(0, |row| row.len());
+        assert!(
+            cells.iter().all(|row| row.len() == width),
+            "pattern rows must all be the same width"
+        );
+        Pattern {
+            cells,
+            width,
+            height,
+        }
+    }
+
+    pub fn get(&self, x: usize, y: usize) -> Option<&Option<A>> {
+        self.cells.get(y).and_then(|row| row.get(x))
+    }
+
+    pub fn matches(&self, view: &XYView<'_, A>) -> bool
+    where
+        A: PartialEq,
+    {
+        view.width == self.width
+            && view.height == self.height
+            && self.cells.iter().zip(view.rows()).all(|(expected, row)| {
+                expected
+                    .iter()
+                    .zip(row)
+                    .all(|(cell, value)| cell.as_ref().is_none_or(|cell| cell == value))
+            })
+    }
+
+    /// Rotates the pattern 90 degrees clockwise.
+    pub fn rotate(&self) -> Pattern<A>
+    where
+        A: Clone,
+    {
+        let cells = (0..self.width)
+            .map(|x| {
+                (0..self.height)
+                    .rev()
+                    .map(|y| self.cells[y][x].clone())
+                    .collect()
+            })
+            .collect();
+        Pattern {
+            cells,
+            width: self.height,
+            height: self.width,
+        }
+    }
+
+    /// Mirrors the pattern left to right.
+    pub fn flip(&self) -> Pattern<A>
+    where
+        A: Clone,
+    {
+        let cells = self
+            .cells
+            .iter()
+            .map(|row| row.iter().rev().cloned().collect())
+            .collect();
+        Pattern {
+            cells,
+            width: self.width,
+            height: self.height,
+        }
+    }
+
+    /// Every distinct rotation and reflection of the pattern, starting with the pattern itself.
+    pub fn orientations(&self) -> Vec<Pattern<A>>
+    where
+        A: Clone + PartialEq,
+    {
+        let mut orientations: Vec<Pattern<A>> = Vec::with_capacity(8);
+        for mut current in [self.clone(), self.flip()] {
+            for _ in 0..4 {
+                let next = current.rotate();
+                if !orientations.contains(&current) {
+                    orientations.push(current);
+                }
+                current = next;
+            }
+        }
+        orientations
+    }
+}
+
+impl Pattern<char> {
+    /// Builds a pattern from lines of text, treating `wildcard` as a cell that matches anything.
+    pub fn from_chars(str: &str, wildcard: char) -> Pattern<char> {
+        Pattern::new(
+            str.lines()
+                .map(|line| {
+                    line.chars()
+                        .map(|ch| if ch == wildcard { None } else { Some(ch) })
+                        .collect()
+                })
+                .collect(),
+        )
+    }
+
+    /// `word` along both diagonals of a square, like the X-MAS shape for `"MAS"`.
+    pub fn x_shape(word: &str) -> Pattern<char> {
+        let letters: Vec<char> = word.chars().collect();
+        let size = letters.len();
+        let mut cells = vec![vec![None; size]; size];
+        for (i, letter) in letters.iter().enumerate() {
+            cells[i][i] = Some(*letter);
+            cells[size - 1 - i][i] = Some(*letter);
+        }
+        Pattern::new(cells)
+    }
+
+    /// `word` across the middle row and down the middle column of a square, panics for words of
+    /// even length since they have no middle letter to share.
+    pub fn plus_shape(word: &str) -> Pattern<char> {
+        let letters: Vec<char> = word.chars().collect();
+        let size = letters.len();
+        assert!(size % 2 == 1, "a plus shape needs a word of odd length");
+        let mut cells = vec![vec![None; size]; size];
+        for (i, letter) in letters.iter().enumerate() {
+            cells[size / 2][i] = Some(*letter);
+            cells[i][size / 2] = Some(*letter);
+        }
+        Pattern::new(cells)
+    }
+}
+
+impl<A> XYWorld<A> {
+    /// Top left coordinates of every place `pattern` matches as given.
+    pub fn find_pattern(&self, pattern: &Pattern<A>) -> Vec<(usize, usize)>
+    where
+        A: PartialEq,
+    {
+        self.windows(pattern.width, pattern.height)
+            .filter(|view| pattern.matches(view))
+            .map(|view| view.origin())
+            .collect()
+    }
+
+    /// Top left coordinates of every match of any rotation or reflection of `pattern`, a position
+    /// is reported once for each distinct orientation that matches there.
+    pub fn find_pattern_oriented(&self, pattern: &Pattern<A>) -> Vec<(usize, usize)>
+    where
+        A: Clone + PartialEq,
+    {
+        pattern
+            .orientations()
+            .iter()
+            .flat_map(|orientation| self.find_pattern(orientation))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[rustfmt::skip]
+    const X_MAS: &str = "\
+M.S
+.A.
+M.S";
+
+    #[test]
+    fn test_pattern_orientations() {
+        assert_eq!(Pattern::from_chars("XMAS", '.').orientations().len(), 4);
+        assert_eq!(Pattern::from_chars(X_MAS, '.').orientations().len(), 4);
+        assert_eq!(Pattern::from_chars("XM\n.A", '.').orientations().len(), 8);
+    }
+
+    #[test]
+    fn test_shapes() {
+        assert_eq!(Pattern::x_shape("MAS"), Pattern::from_chars(X_MAS, '.'));
+        let plus = Pattern::plus_shape("MAS");
+        assert_eq!(plus, Pattern::from_chars(".M.\nMAS\n.S.", '.'));
+        let world = XYWorld::<char>::parse(".S.\nSAM\n.M.").unwrap();
+        assert_eq!(world.find_pattern_oriented(&plus), vec![(0, 0)]);
+    }
+}
@@ -0,0 +1,222 @@
+use crate::{Rect, XYWorld};
+
+const ORTHOGONAL: [(isize, isize); 4] = [(0, 1), (-1, 0), (0, -1), (1, 0)];
+const DIAGONAL: [(isize, isize); 4] = [(1, 1), (-1, 1), (-1, -1), (1, -1)];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Connectivity {
+    /// Cells sharing an edge are connected.
+    Four,
+    /// Cells sharing an edge or a corner are connected.
+    Eight,
+}
+
+impl Connectivity {
+    fn offsets(&self) -> &'static [(isize, isize)] {
+        const EIGHT: [(isize, isize); 8] = [
+            (0, 1),
+            (-1, 0),
+            (0, -1),
+            (1, 0),
+            (1, 1),
+            (-1, 1),
+            (-1, -1),
+            (1, -1),
+        ];
+        match self {
+            Connectivity::Four => &ORTHOGONAL,
+            Connectivity::Eight => &EIGHT,
+        }
+    }
+}
+
+/// A single connected component and its metrics.
+#[derive(Debug, Clone)]
+pub struct Region {
+    pub label: usize,
+    pub cells: Vec<(usize, usize)>,
+    pub perimeter: usize,
+    /// Number of straight edges, found by counting the region's corners.
+    pub sides: usize,
+    pub bounding_box: Rect<usize>,
+}
+
+impl Region {
+    pub fn area(&self) -> usize {
+        self.cells.len()
+    }
+
+    pub fn centroid(&self) -> (f64, f64) {
+        let (sum_x, sum_y) = self
+            .cells
+            .iter()
+            .fold((0, 0), |(sx, sy), (x, y)| (sx + x, sy + y));
+        let area = self.area() as f64;
+        (sum_x as f64 / area, sum_y as f64 / area)
+    }
+}
+
+/// The result of labelling a grid, every labelled cell points into `regions`.
+pub struct Regions {
+    labels: XYWorld<Option<usize>>,
+    regions: Vec<Region>,
+}
+
+impl Regions {
+    pub fn label(&self, x: usize, y: usize) -> Option<usize> {
+        self.labels.get(x, y).copied().flatten()
+    }
+
+    pub fn region_at(&self, x: usize, y: usize) -> Option<&Region> {
+        self.label(x, y).map(|label| &self.regions[label])
+    }
+
+    pub fn get(&self, label: usize) -> Option<&Region> {
+        self.regions.get(label)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Region> {
+        self.regions.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.regions.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.regions.is_empty()
+    }
+
+    fn same(&self, label: usize, x: isize, y: isize) -> bool {
+        self.labels
+            .get_isize(x, y)
+            .is_some_and(|other| *other == Some(label))
+    }
+
+    fn measure(&mut self, label: usize) {
+        let mut perimeter = 0;
+        let mut sides = 0;
+        for &(x, y) in &self.regions[label].cells {
+            let (x, y) = (x as isize, y as isize);
+            perimeter += ORTHOGONAL
+                .iter()
+                .filter(|(dx, dy)| !self.same(label, x + dx, y + dy))
+                .count();
+            for (dx, dy) in DIAGONAL {
+                let horizontal = self.same(label, x + dx, y);
+                let vertical = self.same(label, x, y + dy);
+                let diagonal = self.same(label, x + dx, y + dy);
+                // an outside corner or an inside corner
+                if (!horizontal && !vertical) || (horizontal && vertical && !diagonal) {
+                    sides += 1;
+                }
+            }
+        }
+        let region = &mut self.regions[label];
+        region.perimeter = perimeter;
+        region.sides = sides;
+    }
+}
+
+impl<A> XYWorld<A> {
+    /// Labels connected components of cells that hold equal values.
+    pub fn label_regions(&self, connectivity: Connectivity) -> Regions
+    where
+        A: PartialEq,
+    {
+        self.label_regions_with(connectivity, |_| true, |a, b| a == b)
+    }
+
+    /// Labels connected components of cells matching `predicate`, cells that don't match are left
+    /// unlabelled.
+    pub fn label_regions_by<F>(&self, connectivity: Connectivity, predicate: F) -> Regions
+    where
+        F: Fn(&A) -> bool,
+    {
+        self.label_regions_with(connectivity, predicate, |_, _| true)
+    }
+
+    fn label_regions_with<F, G>(&self, connectivity: Connectivity, include: F, joins: G) -> Regions
+    where
+        F: Fn(&A) -> bool,
+        G: Fn(&A, &A) -> bool,
+    {
+        let mut result = Regions {
+            labels: XYWorld {
+                world: vec![vec![None; self.width]; self.height],
+                width: self.width,
+                height: self.height,
+            },
+            regions: vec![],
+        };
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let value = self.get_unsafe(x, y);
+                if result.labels.get_unsafe(x, y).is_some() || !include(value) {
+                    continue;
+                }
+                let label = result.regions.len();
+                let mut cells = vec![];
+                let mut bounds = ((x, y), (x, y));
+                let mut current = vec![(x, y)];
+                result.labels.update_unsafe(x, y, Some(label));
+                while let Some((cx, cy)) = current.pop() {
+                    cells.push((cx, cy));
+                    bounds.0 = (bounds.0 .0.min(cx), bounds.0 .1.min(cy));
+                    bounds.1 = (bounds.1 .0.max(cx), bounds.1 .1.max(cy));
+                    for (dx, dy) in connectivity.offsets() {
+                        if let (Some(nx), Some(ny)) =
+                            (cx.checked_add_signed(*dx), cy.checked_add_signed(*dy))
+                        {
+                            if let Some(other) = self.get(nx, ny) {
+                                if result.labels.get_unsafe(nx, ny).is_none()
+                                    && include(other)
+                                    && joins(value, other)
+                                {
+                                    result.labels.update_unsafe(nx, ny, Some(label));
+                                    current.push((nx, ny));
+                                }
+                            }
+                        }
+                    }
+                }
+                result.regions.push(Region {
+                    label,
+                    cells,
+                    perimeter: 0,
+                    sides: 0,
+                    bounding_box: Rect::new(bounds.0, bounds.1),
+                });
+                result.measure(label);
+            }
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_region_metrics() {
+        let world = XYWorld::<char>::parse("AAAA\nBBCD\nBBCC\nEEEC").unwrap();
+        let regions = world.label_regions(Connectivity::Four);
+        assert_eq!(regions.len(), 5);
+        let c = regions.region_at(2, 1).unwrap();
+        assert_eq!((c.area(), c.perimeter, c.sides), (4, 10, 8));
+        assert_eq!(*c.bounding_box.bot_left(), (2, 1));
+        assert_eq!(*c.bounding_box.top_right(), (3, 3));
+        assert_eq!(c.centroid(), (2.5, 2.0));
+    }
+
+    #[test]
+    fn test_label_regions_by() {
+        let world = XYWorld::<char>::parse("#..\n.#.\n..#").unwrap();
+        let four = world.label_regions_by(Connectivity::Four, |ch| *ch == '#');
+        let eight = world.label_regions_by(Connectivity::Eight, |ch| *ch == '#');
+        assert_eq!(four.len(), 3);
+        assert_eq!(eight.len(), 1);
+        assert_eq!(eight.label(1, 0), None);
+    }
+}
@@ -0,0 +1,180 @@
+use std::collections::{HashMap, VecDeque};
+
+use crate::XYWorld;
+
+/// All 8 reading directions, starting right and going clockwise.
+pub const DIRECTIONS: [(isize, isize); 8] = [
+    (1, 0),
+    (1, 1),
+    (0, 1),
+    (-1, 1),
+    (-1, 0),
+    (-1, -1),
+    (0, -1),
+    (1, -1),
+];
+
+/// A word found in a grid, reading `len` cells from `start` along `direction`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WordMatch {
+    /// Index of the word in the list given to `WordSearch::new`.
+    pub word: usize,
+    pub start: (usize, usize),
+    pub direction: (isize, isize),
+    pub len: usize,
+}
+
+impl WordMatch {
+    pub fn cells(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
+        let (x, y) = (self.start.0 as isize, self.start.1 as isize);
+        let (dx, dy) = self.direction;
+        (0..self.len as isize).map(move |i| ((x + dx * i) as usize, (y + dy * i) as usize))
+    }
+}
+
+#[derive(Debug, Default)]
+struct Node {
+    next: HashMap<char, usize>,
+    fail: usize,
+    /// Words ending here, including those reached through failure links.
+    words: Vec<usize>,
+}
+
+/// An Aho-Corasick automaton over a list of words, run once along every line of a grid in each
+/// direction so many words cost about the same as one.
+#[derive(Debug)]
+pub struct WordSearch {
+    nodes: Vec<Node>,
+    lengths: Vec<usize>,
+}
+
+impl WordSearch {
+    pub fn new<S: AsRef<str>>(words: &[S]) -> WordSearch {
+        let mut nodes = vec![Node::default()];
+        let mut lengths = Vec::with_capacity(words.len());
+        for (index, word) in words.iter().enumerate() {
+            let mut node = 0;
+            for ch in word.as_ref().chars() {
+                node = match nodes[node].next.get(&ch) {
+                    Some(next) => *next,
+                    None => {
+                        nodes.push(Node::default());
+                        let next = nodes.len() - 1;
+                        nodes[node].next.insert(ch, next);
+                        next
+                    }
+                };
+            }
+            lengths.push(word.as_ref().chars().count());
+            if node != 0 {
+                nodes[node].words.push(index);
+            }
+        }
+
+        let mut queue: VecDeque<usize> = nodes[0].next.values().copied().collect();
+        while let Some(node) = queue.pop_front() {
+            let edges: Vec<(char, usize)> = nodes[node]
+                .next
+                .iter()
+                .map(|(ch, next)| (*ch, *next))
+                .collect();
+            for (ch, next) in edges {
+                let mut fail = nodes[node].fail;
+                while fail != 0 && !nodes[fail].next.contains_key(&ch) {
+                    fail = nodes[fail].fail;
+                }
+                let fail = nodes[fail].next.get(&ch).copied().unwrap_or(0);
+                nodes[next].fail = fail;
+                let inherited = nodes[fail].words.clone();
+                nodes[next].words.extend(inherited);
+                queue.push_back(next);
+            }
+        }
+        WordSearch { nodes, lengths }
+    }
+
+    fn advance(&self, mut node: usize, ch: char) -> usize {
+        loop {
+            if let Some(next) = self.nodes[node].next.get(&ch) {
+                return *next;
+            }
+            if node == 0 {
+                return 0;
+            }
+            node = self.nodes[node].fail;
+        }
+    }
+
+    /// Every occurrence of every word in all 8 directions. A palindrome is found once each way,
+    /// and a single letter word once per direction.
+    pub fn find(&self, world: &XYWorld<char>) -> Vec<WordMatch> {
+        DIRECTIONS
+            .iter()
+            .flat_map(|direction| self.find_in_direction(world, *direction))
+            .collect()
+    }
+
+    pub fn find_in_direction(
+        &self,
+        world: &XYWorld<char>,
+        (dx, dy): (isize, isize),
+    ) -> Vec<WordMatch> {
+        let mut matches = vec![];
+        for y in 0..world.height as isize {
+            for x in 0..world.width as isize {
+                // Only start from cells at the beginning of a line in this direction.
+                if world.contains(x - dx, y - dy) {
+                    continue;
+                }
+                let mut node = 0;
+                let (mut cx, mut cy) = (x, y);
+                while let Some(ch) = world.get_isize(cx, cy) {
+                    node = self.advance(node, *ch);
+                    for word in &self.nodes[node].words {
+                        let back = self.lengths[*word] as isize - 1;
+                        matches.push(WordMatch {
+                            word: *word,
+                            start: ((cx - dx * back) as usize, (cy - dy * back) as usize),
+                            direction: (dx, dy),
+                            len: self.lengths[*word],
+                        });
+                    }
+                    cx += dx;
+                    cy += dy;
+                }
+            }
+        }
+        matches
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_word_search() {
+        let world = XYWorld::<char>::parse("CAT\nOXO\nTAC").unwrap();
+        let search = WordSearch::new(&["CAT", "AT", "TOT", "DOG"]);
+        let mut found = search
+            .find(&world)
+            .iter()
+            .map(|found| (found.word, found.start, found.direction))
+            .collect::<Vec<_>>();
+        found.sort();
+        assert_eq!(
+            found,
+            vec![
+                (0, (0, 0), (1, 0)),
+                (0, (2, 2), (-1, 0)),
+                (1, (1, 0), (1, 0)),
+                (1, (1, 2), (-1, 0)),
+            ]
+        );
+        let cat = search.find_in_direction(&world, (1, 0))[0];
+        assert_eq!(
+            cat.cells().collect::<Vec<_>>(),
+            vec![(0, 0), (1, 0), (2, 0)]
+        );
+    }
+}
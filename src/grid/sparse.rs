@@ -0,0 +1,264 @@
+use std::{
+    collections::HashMap,
+    fmt::{Display, Write},
+};
+
+use crate::{Rect, XYWorld};
+
+/// How coordinates given to a `SparseGrid` are mapped onto stored cells.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Addressing {
+    /// Any coordinate is stored as is.
+    Unbounded,
+    /// Coordinates wrap around a `width` by `height` torus.
+    Toroidal { width: isize, height: isize },
+    /// Coordinates are clamped to the nearest edge of a `width` by `height` grid.
+    Clamped { width: isize, height: isize },
+}
+
+/// A grid that only stores occupied cells, keyed by coordinate.
+#[derive(Debug, Clone)]
+pub struct SparseGrid<T> {
+    cells: HashMap<(isize, isize), T>,
+    addressing: Addressing,
+    bounds: Option<((isize, isize), (isize, isize))>,
+}
+
+impl<T> Default for SparseGrid<T> {
+    fn default() -> Self {
+        SparseGrid::new()
+    }
+}
+
+impl<T> SparseGrid<T> {
+    pub fn new() -> SparseGrid<T> {
+        SparseGrid {
+            cells: HashMap::new(),
+            addressing: Addressing::Unbounded,
+            bounds: None,
+        }
+    }
+
+    /// `None` unless both sizes are positive.
+    pub fn toroidal(width: isize, height: isize) -> Option<SparseGrid<T>> {
+        SparseGrid::with_addressing(Addressing::Toroidal { width, height })
+    }
+
+    /// `None` unless both sizes are positive.
+    pub fn clamped(width: isize, height: isize) -> Option<SparseGrid<T>> {
+        SparseGrid::with_addressing(Addressing::Clamped { width, height })
+    }
+
+    /// `None` if a toroidal or clamped grid has a size below 1, there would be no cell to map to.
+    pub fn with_addressing(addressing: Addressing) -> Option<SparseGrid<T>> {
+        let valid = match addressing {
+            Addressing::Unbounded => true,
+            Addressing::Toroidal { width, height } | Addressing::Clamped { width, height } => {
+                width > 0 && height > 0
+            }
+        };
+        valid.then(|| SparseGrid {
+            addressing,
+            ..SparseGrid::new()
+        })
+    }
+
+    pub fn addressing(&self) -> Addressing {
+        self.addressing
+    }
+
+    /// The stored coordinate `(x, y)` refers to under this grid's addressing mode.
+    pub fn resolve(&self, x: isize, y: isize) -> (isize, isize) {
+        match self.addressing {
+            Addressing::Unbounded => (x, y),
+            Addressing::Toroidal { width, height } => (x.rem_euclid(width), y.rem_euclid(height)),
+            Addressing::Clamped { width, height } => {
+                (x.clamp(0, width - 1), y.clamp(0, height - 1))
+            }
+        }
+    }
+
+    pub fn get(&self, x: isize, y: isize) -> Option<&T> {
+        self.cells.get(&self.resolve(x, y))
+    }
+
+    pub fn get_mut(&mut self, x: isize, y: isize) -> Option<&mut T> {
+        let pos = self.resolve(x, y);
+        self.cells.get_mut(&pos)
+    }
+
+    pub fn contains(&self, x: isize, y: isize) -> bool {
+        self.cells.contains_key(&self.resolve(x, y))
+    }
+
+    /// Stores `value`, returning whatever was at that cell before.
+    pub fn insert(&mut self, x: isize, y: isize, value: T) -> Option<T> {
+        let pos = self.resolve(x, y);
+        self.grow(pos);
+        self.cells.insert(pos, value)
+    }
+
+    pub fn get_or_insert_with<F>(&mut self, x: isize, y: isize, f: F) -> &mut T
+    where
+        F: FnOnce() -> T,
+    {
+        let pos = self.resolve(x, y);
+        self.grow(pos);
+        self.cells.entry(pos).or_insert_with(f)
+    }
+
+    pub fn remove(&mut self, x: isize, y: isize) -> Option<T> {
+        let pos = self.resolve(x, y);
+        let removed = self.cells.remove(&pos)?;
+        // only a cell on the edge of the bounds can shrink them
+        if let Some(((lx, ly), (hx, hy))) = self.bounds {
+            if pos.0 == lx || pos.0 == hx || pos.1 == ly || pos.1 == hy {
+                self.bounds = None;
+                for pos in self.cells.keys().copied().collect::<Vec<_>>() {
+                    self.grow(pos);
+                }
+            }
+        }
+        Some(removed)
+    }
+
+    fn grow(&mut self, (x, y): (isize, isize)) {
+        self.bounds = Some(match self.bounds {
+            Some(((lx, ly), (hx, hy))) => ((lx.min(x), ly.min(y)), (hx.max(x), hy.max(y))),
+            None => ((x, y), (x, y)),
+        });
+    }
+
+    /// The smallest rectangle holding every occupied cell, inclusive on both corners.
+    pub fn bounds(&self) -> Option<Rect<isize>> {
+        self.bounds
+            .map(|(bot_left, top_right)| Rect::new(bot_left, top_right))
+    }
+
+    pub fn len(&self) -> usize {
+        self.cells.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cells.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = ((isize, isize), &T)> {
+        self.cells.iter().map(|(pos, value)| (*pos, value))
+    }
+
+    pub fn values(&self) -> impl Iterator<Item = &T> {
+        self.cells.values()
+    }
+
+    /// The area `to_world` covers, the full grid for wrapping and clamped grids and the occupied
+    /// bounds otherwise.
+    fn extent(&self) -> Option<((isize, isize), (isize, isize))> {
+        match self.addressing {
+            Addressing::Unbounded => self.bounds,
+            Addressing::Toroidal { width, height } | Addressing::Clamped { width, height } => {
+                Some(((0, 0), (width - 1, height - 1)))
+            }
+        }
+    }
+
+    /// Converts to a dense world, filling unoccupied cells with `empty`. The world's top left is
+    /// the top left of the occupied bounds for unbounded grids.
+    pub fn to_world(&self, empty: T) -> Option<XYWorld<T>>
+    where
+        T: Clone,
+    {
+        let ((lx, ly), (hx, hy)) = self.extent()?;
+        let world = (ly..=hy)
+            .map(|y| {
+                (lx..=hx)
+                    .map(|x| self.cells.get(&(x, y)).unwrap_or(&empty).clone())
+                    .collect()
+            })
+            .collect();
+        Some(XYWorld {
+            world,
+            width: (hx - lx + 1) as usize,
+            height: (hy - ly + 1) as usize,
+        })
+    }
+
+    /// Collects every cell of `world` accepted by `f`.
+    pub fn from_world<A, F>(world: &XYWorld<A>, f: F) -> SparseGrid<T>
+    where
+        F: Fn(&A) -> Option<T>,
+    {
+        let mut grid = SparseGrid::new();
+        for (y, row) in world.world.iter().enumerate() {
+            for (x, value) in row.iter().enumerate() {
+                if let Some(value) = f(value) {
+                    grid.insert(x as isize, y as isize, value);
+                }
+            }
+        }
+        grid
+    }
+}
+
+impl<T> From<XYWorld<T>> for SparseGrid<T> {
+    fn from(world: XYWorld<T>) -> Self {
+        let mut grid = SparseGrid::new();
+        for (y, row) in world.world.into_iter().enumerate() {
+            for (x, value) in row.into_iter().enumerate() {
+                grid.insert(x as isize, y as isize, value);
+            }
+        }
+        grid
+    }
+}
+
+impl<T: Display> Display for SparseGrid<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let Some(((lx, ly), (hx, hy))) = self.bounds {
+            for y in ly..=hy {
+                for x in lx..=hx {
+                    match self.cells.get(&(x, y)) {
+                        Some(value) => write!(f, "{}", value)?,
+                        None => f.write_char('.')?,
+                    }
+                }
+                if y < hy {
+                    f.write_char('\n')?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sparse_grid() {
+        let mut torus: SparseGrid<char> = SparseGrid::toroidal(4, 3).unwrap();
+        torus.insert(-1, 4, 'a');
+        assert_eq!(torus.get(3, 1), Some(&'a'));
+        let mut clamped: SparseGrid<char> = SparseGrid::clamped(4, 3).unwrap();
+        clamped.insert(10, -5, 'b');
+        assert_eq!(clamped.get(3, 0), Some(&'b'));
+        assert!(SparseGrid::<char>::toroidal(0, 3).is_none());
+        assert!(SparseGrid::<char>::clamped(4, 0).is_none());
+        assert!(SparseGrid::<char>::clamped(-4, 3).is_none());
+
+        let mut grid: SparseGrid<char> = SparseGrid::new();
+        grid.insert(-2, 0, 'x');
+        grid.insert(1, 1, 'y');
+        grid.insert(0, 3, 'z');
+        assert_eq!(grid.to_string(), "x...\n...y\n....\n..z.");
+        grid.remove(0, 3);
+        assert_eq!(grid.bounds(), Some(Rect::new((-2, 0), (1, 1))));
+        let world = grid.to_world(' ').unwrap();
+        assert_eq!(world.to_string(), "x   \n   y");
+        assert_eq!(
+            SparseGrid::from_world(&world, |ch| (*ch != ' ').then_some(*ch)).len(),
+            2
+        );
+    }
+}
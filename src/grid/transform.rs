@@ -0,0 +1,207 @@
+use crate::XYWorld;
+
+impl<A> XYWorld<A> {
+    fn from_fn<F>(width: usize, height: usize, f: F) -> XYWorld<A>
+    where
+        F: Fn(usize, usize) -> A,
+    {
+        let world = (0..height)
+            .map(|y| (0..width).map(|x| f(x, y)).collect())
+            .collect();
+        XYWorld {
+            world,
+            height,
+            width,
+        }
+    }
+
+    /// Rotates the world a quarter turn clockwise.
+    pub fn rotate_90(&self) -> XYWorld<A>
+    where
+        A: Clone,
+    {
+        XYWorld::from_fn(self.height, self.width, |x, y| {
+            self.get_unsafe(y, self.height - 1 - x).clone()
+        })
+    }
+
+    pub fn rotate_180(&self) -> XYWorld<A>
+    where
+        A: Clone,
+    {
+        XYWorld::from_fn(self.width, self.height, |x, y| {
+            self.get_unsafe(self.width - 1 - x, self.height - 1 - y)
+                .clone()
+        })
+    }
+
+    /// Rotates the world a quarter turn anti-clockwise.
+    pub fn rotate_270(&self) -> XYWorld<A>
+    where
+        A: Clone,
+    {
+        XYWorld::from_fn(self.height, self.width, |x, y| {
+            self.get_unsafe(self.width - 1 - y, x).clone()
+        })
+    }
+
+    pub fn transpose(&self) -> XYWorld<A>
+    where
+        A: Clone,
+    {
+        XYWorld::from_fn(self.height, self.width, |x, y| {
+            self.get_unsafe(y, x).clone()
+        })
+    }
+
+    /// Mirrors the world left to right.
+    pub fn flip_horizontal(&self) -> XYWorld<A>
+    where
+        A: Clone,
+    {
+        XYWorld::from_fn(self.width, self.height, |x, y| {
+            self.get_unsafe(self.width - 1 - x, y).clone()
+        })
+    }
+
+    /// Mirrors the world top to bottom.
+    pub fn flip_vertical(&self) -> XYWorld<A>
+    where
+        A: Clone,
+    {
+        XYWorld::from_fn(self.width, self.height, |x, y| {
+            self.get_unsafe(x, self.height - 1 - y).clone()
+        })
+    }
+
+    /// Surrounds the world with a border `amount` cells thick.
+    pub fn pad(&self, amount: usize, fill: A) -> XYWorld<A>
+    where
+        A: Clone,
+    {
+        XYWorld::from_fn(
+            self.width + amount * 2,
+            self.height + amount * 2,
+            |x, y| match (x.checked_sub(amount), y.checked_sub(amount)) {
+                (Some(x), Some(y)) if x < self.width && y < self.height => {
+                    self.get_unsafe(x, y).clone()
+                }
+                _ => fill.clone(),
+            },
+        )
+    }
+
+    /// Crops to the smallest rectangle containing every cell matching `predicate`, returns `None`
+    /// when nothing matches.
+    pub fn crop_to<F>(&self, predicate: F) -> Option<XYWorld<A>>
+    where
+        A: Clone,
+        F: Fn(&A) -> bool,
+    {
+        let mut bounds: Option<((usize, usize), (usize, usize))> = None;
+        for (y, row) in self.world.iter().enumerate() {
+            for (x, value) in row.iter().enumerate() {
+                if predicate(value) {
+                    bounds = Some(match bounds {
+                        Some(((lx, ly), (hx, hy))) => {
+                            ((lx.min(x), ly.min(y)), (hx.max(x), hy.max(y)))
+                        }
+                        None => ((x, y), (x, y)),
+                    });
+                }
+            }
+        }
+        let ((lx, ly), (hx, hy)) = bounds?;
+        Some(XYWorld::from_fn(hx - lx + 1, hy - ly + 1, |x, y| {
+            self.get_unsafe(lx + x, ly + y).clone()
+        }))
+    }
+
+    /// Replaces every cell with the run of cells `f` returns for it, laid out left to right.
+    ///
+    /// Panics if the rows end up with different widths.
+    pub fn expand_horizontal<B, I, F>(&self, f: F) -> XYWorld<B>
+    where
+        F: Fn(&A) -> I,
+        I: IntoIterator<Item = B>,
+    {
+        let world: Vec<Vec<B>> = self
+            .world
+            .iter()
+            .map(|row| row.iter().flat_map(&f).collect())
+            .collect();
+        let width = world.first().map_or(0, |row| row.len());
+        assert!(
+            world.iter().all(|row| row.len() == width),
+            "expansion produced rows of different widths"
+        );
+        XYWorld {
+            world,
+            height: self.height,
+            width,
+        }
+    }
+
+    /// Replaces every cell with the run of cells `f` returns for it, laid out top to bottom.
+    ///
+    /// Panics if the cells of a row expand to different heights.
+    pub fn expand_vertical<B, I, F>(&self, f: F) -> XYWorld<B>
+    where
+        F: Fn(&A) -> I,
+        I: IntoIterator<Item = B>,
+    {
+        let mut world: Vec<Vec<B>> = vec![];
+        for row in &self.world {
+            let mut expanded: Vec<Vec<B>> = vec![];
+            for (x, value) in row.iter().enumerate() {
+                for (dy, cell) in f(value).into_iter().enumerate() {
+                    if x == 0 {
+                        expanded.push(Vec::with_capacity(self.width));
+                    }
+                    assert!(
+                        dy < expanded.len(),
+                        "expansion produced columns of different heights"
+                    );
+                    expanded[dy].push(cell);
+                }
+            }
+            assert!(
+                expanded.iter().all(|row| row.len() == self.width),
+                "expansion produced columns of different heights"
+            );
+            world.extend(expanded);
+        }
+        let height = world.len();
+        XYWorld {
+            world,
+            height,
+            width: self.width,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_transformations() {
+        let world = XYWorld::<char>::parse("ab\ncd\nef").unwrap();
+        assert_eq!(world.rotate_90().to_string(), "eca\nfdb");
+        assert_eq!(world.rotate_180().to_string(), "fe\ndc\nba");
+        assert_eq!(world.rotate_270().to_string(), "bdf\nace");
+        assert_eq!(world.transpose().to_string(), "ace\nbdf");
+        assert_eq!(world.flip_horizontal().to_string(), "ba\ndc\nfe");
+        assert_eq!(world.flip_vertical().to_string(), "ef\ncd\nab");
+        assert_eq!(
+            world.pad(1, '.').to_string(),
+            "....\n.ab.\n.cd.\n.ef.\n...."
+        );
+        let cropped = world.pad(2, '.').crop_to(|ch| *ch == 'c' || *ch == 'f');
+        assert_eq!(cropped.unwrap().to_string(), "cd\nef");
+        let tall = world.expand_vertical(|ch| [*ch, ch.to_ascii_uppercase()]);
+        assert_eq!(tall.to_string(), "ab\nAB\ncd\nCD\nef\nEF");
+        let wide = world.expand_horizontal(|ch| [*ch; 2]);
+        assert_eq!(wide.to_string(), "aabb\nccdd\neeff");
+    }
+}
@@ -0,0 +1,166 @@
+use std::fmt::{Display, Write};
+
+use crate::XYWorld;
+
+/// A borrowed rectangular region of an `XYWorld`, addressed relative to its own top left.
+#[derive(Clone, Copy)]
+pub struct XYView<'a, A> {
+    world: &'a XYWorld<A>,
+    x: usize,
+    y: usize,
+    pub width: usize,
+    pub height: usize,
+}
+
+/// Whether `len` cells from `start` stay within `size`, without overflowing.
+fn fits(start: usize, len: usize, size: usize) -> bool {
+    start.checked_add(len).is_some_and(|end| end <= size)
+}
+
+impl<'a, A> XYView<'a, A> {
+    /// Position of the view's top left cell in the underlying world.
+    pub fn origin(&self) -> (usize, usize) {
+        (self.x, self.y)
+    }
+
+    pub fn get(&self, x: usize, y: usize) -> Option<&'a A> {
+        if x < self.width && y < self.height {
+            self.world.get(self.x + x, self.y + y)
+        } else {
+            None
+        }
+    }
+
+    pub fn get_isize(&self, x: isize, y: isize) -> Option<&'a A> {
+        let x_usize: usize = x.try_into().ok()?;
+        let y_usize: usize = y.try_into().ok()?;
+        self.get(x_usize, y_usize)
+    }
+
+    pub fn get_unsafe(&self, x: usize, y: usize) -> &'a A {
+        debug_assert!(x < self.width && y < self.height);
+        self.world.get_unsafe(self.x + x, self.y + y)
+    }
+
+    pub fn row(&self, y: usize) -> Option<&'a [A]> {
+        if y < self.height {
+            Some(&self.world.world[self.y + y][self.x..self.x + self.width])
+        } else {
+            None
+        }
+    }
+
+    pub fn rows(&self) -> impl Iterator<Item = &'a [A]> + '_ {
+        (0..self.height).filter_map(|y| self.row(y))
+    }
+
+    /// Iterates over `((x, y), value)` with coordinates relative to the view.
+    pub fn iter(&self) -> impl Iterator<Item = ((usize, usize), &'a A)> + '_ {
+        self.rows()
+            .enumerate()
+            .flat_map(|(y, row)| row.iter().enumerate().map(move |(x, v)| ((x, y), v)))
+    }
+
+    /// Narrows the view further, returns `None` if the region does not fit inside this view.
+    pub fn view(&self, x: usize, y: usize, width: usize, height: usize) -> Option<XYView<'a, A>> {
+        if fits(x, width, self.width) && fits(y, height, self.height) {
+            Some(XYView {
+                world: self.world,
+                x: self.x + x,
+                y: self.y + y,
+                width,
+                height,
+            })
+        } else {
+            None
+        }
+    }
+
+    pub fn to_world(&self) -> XYWorld<A>
+    where
+        A: Clone,
+    {
+        XYWorld {
+            world: self.rows().map(|row| row.to_vec()).collect(),
+            width: self.width,
+            height: self.height,
+        }
+    }
+}
+
+impl<A: Display> Display for XYView<'_, A> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (y, row) in self.rows().enumerate() {
+            for value in row {
+                write!(f, "{}", value)?;
+            }
+            if y + 1 < self.height {
+                f.write_char('\n')?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<A> XYWorld<A> {
+    /// Borrows the `width` by `height` region starting at `(x, y)`, returns `None` if it does not
+    /// fit inside the world.
+    pub fn view(&self, x: usize, y: usize, width: usize, height: usize) -> Option<XYView<'_, A>> {
+        if fits(x, width, self.width) && fits(y, height, self.height) {
+            Some(XYView {
+                world: self,
+                x,
+                y,
+                width,
+                height,
+            })
+        } else {
+            None
+        }
+    }
+
+    pub fn as_view(&self) -> XYView<'_, A> {
+        XYView {
+            world: self,
+            x: 0,
+            y: 0,
+            width: self.width,
+            height: self.height,
+        }
+    }
+
+    /// Every `width` by `height` view that fits in the world, row by row from the top left.
+    pub fn windows(&self, width: usize, height: usize) -> impl Iterator<Item = XYView<'_, A>> {
+        let xs = (self.width + 1).saturating_sub(width);
+        let ys = (self.height + 1).saturating_sub(height);
+        let empty = width == 0 || height == 0;
+        (0..ys)
+            .flat_map(move |y| (0..xs).map(move |x| (x, y)))
+            .filter(move |_| !empty)
+            .map(move |(x, y)| XYView {
+                world: self,
+                x,
+                y,
+                width,
+                height,
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_windows() {
+        let world = XYWorld::<char>::parse("abc\ndef").unwrap();
+        let windows = world
+            .windows(2, 2)
+            .map(|view| view.to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(windows, vec!["ab\nde", "bc\nef"]);
+        assert_eq!(world.windows(4, 1).count(), 0);
+        assert!(world.view(1, 0, usize::MAX, 1).is_none());
+        assert!(world.as_view().view(0, 1, 1, usize::MAX).is_none());
+    }
+}
@@ -1,4 +1,8 @@
+pub mod grid;
+pub mod math;
+pub mod parse;
 pub mod template;
+pub mod vm;
 
 use std::{
     fmt::{Debug, Display, Write},
@@ -14,16 +18,18 @@ pub struct BinaryGrid {
 }
 
 impl BinaryGrid {
+    /// Lenient parse, short rows are padded with unset cells up to the longest row.
     pub fn from_str<F>(str: &str, f: F) -> BinaryGrid
     where
         F: Fn(char) -> bool,
     {
-        let mut world: Vec<Vec<bool>> = Vec::new();
-        let mut width = 0;
-        for char in str.lines().map(|line| line.chars()) {
-            let row = char.map(|ch| f(ch)).collect::<Vec<_>>();
-            width = row.len();
-            world.push(row);
+        let mut world: Vec<Vec<bool>> = str
+            .lines()
+            .map(|line| line.chars().map(&f).collect())
+            .collect();
+        let width = world.iter().map(|row| row.len()).max().unwrap_or(0);
+        for row in &mut world {
+            row.resize(width, false);
         }
         let height = world.len();
         BinaryGrid {
@@ -38,9 +44,7 @@ impl BinaryGrid {
             let x = x as usize;
             let y = y as usize;
             if x < self.width && y < self.height {
-                self.world
-                    .get_mut(y as usize)
-                    .and_then(|row| row.get_mut(x as usize))
+                self.world.get_mut(y).and_then(|row| row.get_mut(x))
             } else {
                 None
             }
@@ -54,7 +58,7 @@ impl BinaryGrid {
             let x = x as usize;
             let y = y as usize;
             if x < self.width && y < self.height {
-                Some(self.world[y as usize][x as usize])
+                Some(self.world[y][x])
             } else {
                 None
             }
@@ -82,12 +86,20 @@ impl Display for BinaryGrid {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct Rect<A> {
     bot_left: (A, A),
     top_right: (A, A),
 }
 impl<A> Rect<A> {
+    pub fn bot_left(&self) -> &(A, A) {
+        &self.bot_left
+    }
+
+    pub fn top_right(&self) -> &(A, A) {
+        &self.top_right
+    }
+
     pub fn contains(&self, x: A, y: A) -> bool
     where
         A: PartialOrd,
@@ -244,7 +256,7 @@ impl<A> XYWorld<A> {
     }
 
     pub fn is_outside(&self, x: isize, y: isize) -> bool {
-        x < 0 || y < 0 || x > self.width as isize || y > self.height as isize
+        x < 0 || y < 0 || x >= self.width as isize || y >= self.height as isize
     }
 
     pub fn find_first(&self, ch: &A) -> Option<(usize, usize)>
@@ -332,6 +344,38 @@ impl<A> XYWorld<A> {
         }
     }
 
+    pub fn filled(width: usize, height: usize, value: A) -> XYWorld<A>
+    where
+        A: Clone,
+    {
+        let world = vec![vec![value; width]; height];
+        XYWorld {
+            world,
+            height,
+            width,
+        }
+    }
+
+    pub fn map<B, F>(&self, f: F) -> XYWorld<B>
+    where
+        F: Fn(&A) -> B,
+    {
+        let world = self
+            .world
+            .iter()
+            .map(|row| row.iter().map(&f).collect())
+            .collect();
+        XYWorld {
+            world,
+            height: self.height,
+            width: self.width,
+        }
+    }
+
+    pub fn values(&self) -> impl Iterator<Item = &A> {
+        self.world.iter().flatten()
+    }
+
     pub fn blank(width: usize, height: usize) -> XYWorld<char> {
         let world = vec![vec!['.'; width]; height];
         XYWorld {
@@ -341,18 +385,22 @@ impl<A> XYWorld<A> {
         }
     }
 
-    pub fn from_str<B: FromStr>(str: &str) -> XYWorld<B> {
+    /// Lenient parse that stores `B::default()` for characters `B` can't parse and for the missing
+    /// end of short rows, so every cell stays in its column. See `XYWorld::parse` for a version that
+    /// reports them.
+    pub fn from_str<B: FromStr + Default + Clone>(str: &str) -> XYWorld<B> {
         let mut world: Vec<Vec<B>> = vec![];
-        let mut width = 0;
         for line in str.lines() {
             let chars = line
                 .chars()
-                .map(|ch| ch.to_string())
-                .filter_map(|ch| ch.parse::<B>().ok())
+                .map(|ch| ch.to_string().parse::<B>().unwrap_or_default())
                 .collect::<Vec<B>>();
-            width = chars.len();
             world.push(chars);
         }
+        let width = world.iter().map(|row| row.len()).max().unwrap_or(0);
+        for row in &mut world {
+            row.resize(width, B::default());
+        }
         let height = world.len();
         XYWorld {
             world,
@@ -363,3 +411,22 @@ impl<A> XYWorld<A> {
 }
 
 // Use this file to add helper functions and additional modules.
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ragged_rows_are_padded() {
+        let world = XYWorld::<u8>::from_str::<u8>("12\n3x4");
+        assert_eq!(world.width, 3);
+        assert_eq!(world.get(2, 0), Some(&0));
+        // The bad cell keeps its column, so the 4 stays under the padding.
+        assert_eq!(world.get(2, 1), Some(&4));
+        assert_eq!(world.to_string(), "120\n304");
+
+        let grid = BinaryGrid::from_str("#.#\n#", |ch| ch == '#');
+        assert_eq!(grid.get(2, 1), Some(false));
+        assert_eq!(grid.to_string(), "#.#\n#..");
+    }
+}
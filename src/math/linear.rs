@@ -0,0 +1,339 @@
+use std::{cmp::Ordering, fmt::Display};
+
+use super::{extended_gcd, gcd, MathError};
+
+/// An exact fraction kept in lowest terms with a positive denominator.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Rational {
+    numerator: i64,
+    denominator: i64,
+}
+
+impl Rational {
+    pub fn new(numerator: i64, denominator: i64) -> Result<Rational, MathError> {
+        if denominator == 0 {
+            return Err(MathError::DivisionByZero);
+        }
+        let divisor =
+            i64::try_from(gcd(numerator, denominator)).map_err(|_| MathError::Overflow)?;
+        let sign = denominator.signum();
+        Ok(Rational {
+            numerator: (numerator / divisor)
+                .checked_mul(sign)
+                .ok_or(MathError::Overflow)?,
+            denominator: (denominator / divisor)
+                .checked_mul(sign)
+                .ok_or(MathError::Overflow)?,
+        })
+    }
+
+    pub fn integer(value: i64) -> Rational {
+        Rational {
+            numerator: value,
+            denominator: 1,
+        }
+    }
+
+    pub fn numerator(&self) -> i64 {
+        self.numerator
+    }
+
+    pub fn denominator(&self) -> i64 {
+        self.denominator
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.numerator == 0
+    }
+
+    /// The value as an integer, `None` if it has a fractional part.
+    pub fn to_integer(&self) -> Option<i64> {
+        (self.denominator == 1).then_some(self.numerator)
+    }
+
+    pub fn checked_add(self, rhs: Rational) -> Result<Rational, MathError> {
+        let numerator = self
+            .numerator
+            .checked_mul(rhs.denominator)
+            .zip(rhs.numerator.checked_mul(self.denominator))
+            .and_then(|(a, b)| a.checked_add(b))
+            .ok_or(MathError::Overflow)?;
+        let denominator = self
+            .denominator
+            .checked_mul(rhs.denominator)
+            .ok_or(MathError::Overflow)?;
+        Rational::new(numerator, denominator)
+    }
+
+    pub fn checked_sub(self, rhs: Rational) -> Result<Rational, MathError> {
+        let negated = rhs.numerator.checked_neg().ok_or(MathError::Overflow)?;
+        self.checked_add(Rational::new(negated, rhs.denominator)?)
+    }
+
+    pub fn checked_mul(self, rhs: Rational) -> Result<Rational, MathError> {
+        let numerator = self
+            .numerator
+            .checked_mul(rhs.numerator)
+            .ok_or(MathError::Overflow)?;
+        let denominator = self
+            .denominator
+            .checked_mul(rhs.denominator)
+            .ok_or(MathError::Overflow)?;
+        Rational::new(numerator, denominator)
+    }
+
+    /// Divides by `rhs`, failing with `DivisionByZero` if it is zero.
+    pub fn checked_div(self, rhs: Rational) -> Result<Rational, MathError> {
+        self.checked_mul(Rational::new(rhs.denominator, rhs.numerator)?)
+    }
+}
+
+impl Display for Rational {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.denominator == 1 {
+            write!(f, "{}", self.numerator)
+        } else {
+            write!(f, "{}/{}", self.numerator, self.denominator)
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LinearSolution {
+    Unique(Vec<Rational>),
+    /// No assignment satisfies every equation.
+    Inconsistent,
+    /// The equations are consistent but don't pin down every variable.
+    Underdetermined,
+}
+
+/// Solves `matrix * x = rhs` exactly with Gauss-Jordan elimination over rationals.
+///
+/// Panics if `matrix` isn't square or doesn't match the length of `rhs`.
+pub fn solve_linear(matrix: &[Vec<i64>], rhs: &[i64]) -> Result<LinearSolution, MathError> {
+    let n = rhs.len();
+    assert!(
+        matrix.len() == n && matrix.iter().all(|row| row.len() == n),
+        "matrix must be square and match the right hand side"
+    );
+    let mut rows: Vec<Vec<Rational>> = matrix
+        .iter()
+        .zip(rhs)
+        .map(|(row, value)| {
+            row.iter()
+                .chain([value])
+                .map(|v| Rational::integer(*v))
+                .collect()
+        })
+        .collect();
+    let mut pivot_row = 0;
+    let mut pivots = vec![];
+    for column in 0..n {
+        let Some(found) = (pivot_row..n).find(|row| !rows[*row][column].is_zero()) else {
+            continue;
+        };
+        rows.swap(pivot_row, found);
+        let pivot = rows[pivot_row][column];
+        for value in rows[pivot_row].iter_mut() {
+            *value = value.checked_div(pivot)?;
+        }
+        let pivot_values = rows[pivot_row].clone();
+        for (index, row) in rows.iter_mut().enumerate() {
+            let factor = row[column];
+            if index == pivot_row || factor.is_zero() {
+                continue;
+            }
+            for (value, pivot_value) in row.iter_mut().zip(&pivot_values).skip(column) {
+                *value = value.checked_sub(pivot_value.checked_mul(factor)?)?;
+            }
+        }
+        pivots.push(column);
+        pivot_row += 1;
+    }
+    if rows[pivot_row..].iter().any(|row| !row[n].is_zero()) {
+        return Ok(LinearSolution::Inconsistent);
+    }
+    if pivots.len() < n {
+        return Ok(LinearSolution::Underdetermined);
+    }
+    Ok(LinearSolution::Unique(
+        rows.iter().map(|row| row[n]).collect(),
+    ))
+}
+
+fn div_floor(a: i64, b: i64) -> i64 {
+    let quotient = a / b;
+    if (a % b != 0) && ((a < 0) != (b < 0)) {
+        quotient - 1
+    } else {
+        quotient
+    }
+}
+
+fn div_ceil(a: i64, b: i64) -> i64 {
+    let quotient = a / b;
+    if (a % b != 0) && ((a < 0) == (b < 0)) {
+        quotient + 1
+    } else {
+        quotient
+    }
+}
+
+/// The cheapest non-negative `(x, y)` with `x * p + y * q = r`.
+fn cheapest_on_line(
+    p: i64,
+    q: i64,
+    r: i64,
+    cost: (i64, i64),
+) -> Result<Option<(i64, i64)>, MathError> {
+    let overflow = |v: Option<i64>| v.ok_or(MathError::Overflow);
+    match (p, q) {
+        (0, 0) => return Ok((r == 0).then_some((0, 0))),
+        (0, q) => return Ok((r % q == 0 && r / q >= 0).then_some((0, r / q))),
+        (p, 0) => return Ok((r % p == 0 && r / p >= 0).then_some((r / p, 0))),
+        _ => {}
+    }
+    let (g, sx, sy) = extended_gcd(p, q);
+    if r % g != 0 {
+        return Ok(None);
+    }
+    let scale = r / g;
+    let (x0, y0) = (
+        overflow(sx.checked_mul(scale))?,
+        overflow(sy.checked_mul(scale))?,
+    );
+    // every solution is (x0 + k * dx, y0 + k * dy)
+    let (dx, dy) = (q / g, -p / g);
+    let (mut low, mut high) = (i64::MIN, i64::MAX);
+    for (start, step) in [(x0, dx), (y0, dy)] {
+        let bound = overflow(start.checked_neg())?;
+        match step.cmp(&0) {
+            Ordering::Greater => low = low.max(div_ceil(bound, step)),
+            Ordering::Less => high = high.min(div_floor(bound, step)),
+            Ordering::Equal => {}
+        }
+    }
+    if low > high {
+        return Ok(None);
+    }
+    let slope = overflow(
+        cost.0
+            .checked_mul(dx)
+            .zip(cost.1.checked_mul(dy))
+            .and_then(|(a, b)| a.checked_add(b)),
+    )?;
+    let k = match slope.cmp(&0) {
+        Ordering::Less if high == i64::MAX => return Err(MathError::Unbounded),
+        Ordering::Less => high,
+        Ordering::Greater if low == i64::MIN => return Err(MathError::Unbounded),
+        Ordering::Greater => low,
+        // every feasible point costs the same, and at least one side is bounded
+        Ordering::Equal if low != i64::MIN => low,
+        Ordering::Equal => high,
+    };
+    let x = overflow(k.checked_mul(dx).and_then(|v| v.checked_add(x0)))?;
+    let y = overflow(k.checked_mul(dy).and_then(|v| v.checked_add(y0)))?;
+    Ok(Some((x, y)))
+}
+
+/// The cheapest non-negative integer `(x, y)` with `x * a + y * b = target`, where each unit of
+/// `x` and `y` costs `cost.0` and `cost.1`.
+///
+/// Independent `a` and `b` give at most one answer. When they are collinear there may be a whole
+/// line of answers, and the cheapest point on it is returned.
+pub fn cheapest_combination(
+    a: (i64, i64),
+    b: (i64, i64),
+    target: (i64, i64),
+    cost: (i64, i64),
+) -> Result<Option<(i64, i64)>, MathError> {
+    let cross = |u: (i64, i64), v: (i64, i64)| {
+        u.0.checked_mul(v.1)
+            .zip(u.1.checked_mul(v.0))
+            .and_then(|(l, r)| l.checked_sub(r))
+            .ok_or(MathError::Overflow)
+    };
+    let determinant = cross(a, b)?;
+    if determinant != 0 {
+        let x = cross(target, b)?;
+        let y = cross(a, target)?;
+        if x % determinant != 0 || y % determinant != 0 {
+            return Ok(None);
+        }
+        let (x, y) = (x / determinant, y / determinant);
+        return Ok((x >= 0 && y >= 0).then_some((x, y)));
+    }
+    // collinear buttons, solve along whichever axis they move on and check the other agrees
+    let axis = if a.0 != 0 || b.0 != 0 { 0 } else { 1 };
+    let pick = |v: (i64, i64)| if axis == 0 { v.0 } else { v.1 };
+    let Some((x, y)) = cheapest_on_line(pick(a), pick(b), pick(target), cost)? else {
+        return Ok(None);
+    };
+    let reaches = |value: i64, da: i64, db: i64| {
+        x.checked_mul(da)
+            .zip(y.checked_mul(db))
+            .and_then(|(l, r)| l.checked_add(r))
+            == Some(value)
+    };
+    Ok((reaches(target.0, a.0, b.0) && reaches(target.1, a.1, b.1)).then_some((x, y)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_division_by_zero() {
+        assert_eq!(Rational::new(1, 0), Err(MathError::DivisionByZero));
+        let half = Rational::new(1, 2).unwrap();
+        assert_eq!(
+            half.checked_div(Rational::integer(0)),
+            Err(MathError::DivisionByZero)
+        );
+        assert_eq!(half.checked_div(half), Ok(Rational::integer(1)));
+        assert_eq!(Rational::new(i64::MIN, i64::MIN), Err(MathError::Overflow));
+        assert_eq!(
+            Rational::new(i64::MIN, 2).unwrap().numerator(),
+            i64::MIN / 2
+        );
+    }
+
+    #[test]
+    fn test_flat_cost_line() {
+        // x - y = 3 costs 3 at every point, which is only bounded on one side.
+        let (x, y) = cheapest_combination((1, 0), (-1, 0), (3, 0), (1, -1))
+            .unwrap()
+            .unwrap();
+        assert!(x >= 0 && y >= 0);
+        assert_eq!(x - y, 3);
+        assert_eq!(
+            cheapest_combination((1, 0), (-1, 0), (3, 0), (1, 0)),
+            Ok(Some((3, 0)))
+        );
+        assert_eq!(
+            cheapest_combination((1, 0), (-1, 0), (3, 0), (0, -1)),
+            Err(MathError::Unbounded)
+        );
+    }
+
+    #[test]
+    fn test_solve_linear() {
+        let solution = solve_linear(&[vec![2, 1], vec![1, 3]], &[3, 5]).unwrap();
+        assert_eq!(
+            solution,
+            LinearSolution::Unique(vec![
+                Rational::new(4, 5).unwrap(),
+                Rational::new(7, 5).unwrap()
+            ])
+        );
+        let singular = [vec![1, 2], vec![2, 4]];
+        assert_eq!(
+            solve_linear(&singular, &[3, 6]),
+            Ok(LinearSolution::Underdetermined)
+        );
+        assert_eq!(
+            solve_linear(&singular, &[3, 7]),
+            Ok(LinearSolution::Inconsistent)
+        );
+    }
+}
@@ -0,0 +1,136 @@
+use std::fmt::Display;
+
+mod linear;
+
+pub use linear::*;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MathError {
+    Overflow,
+    DivisionByZero,
+    /// The modulus of a congruence wasn't positive.
+    InvalidModulus,
+    /// The cost can be made arbitrarily small, so there is no cheapest solution.
+    Unbounded,
+}
+
+impl Display for MathError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MathError::Overflow => write!(f, "arithmetic overflowed"),
+            MathError::DivisionByZero => write!(f, "division by zero"),
+            MathError::InvalidModulus => write!(f, "modulus must be positive"),
+            MathError::Unbounded => write!(f, "cost has no lower bound"),
+        }
+    }
+}
+
+impl std::error::Error for MathError {}
+
+/// Greatest common divisor. Unsigned, since `gcd(i64::MIN, 0)` is `2^63`.
+pub fn gcd(a: i64, b: i64) -> u64 {
+    let (mut a, mut b) = (a.unsigned_abs(), b.unsigned_abs());
+    while b != 0 {
+        (a, b) = (b, a % b);
+    }
+    a
+}
+
+/// Returns `(g, x, y)` where `g = gcd(a, b)` and `a * x + b * y = g`.
+pub fn extended_gcd(a: i64, b: i64) -> (i64, i64, i64) {
+    let (mut old_r, mut r) = (a as i128, b as i128);
+    let (mut old_s, mut s) = (1_i128, 0_i128);
+    let (mut old_t, mut t) = (0_i128, 1_i128);
+    while r != 0 {
+        let quotient = old_r / r;
+        (old_r, r) = (r, old_r - quotient * r);
+        (old_s, s) = (s, old_s - quotient * s);
+        (old_t, t) = (t, old_t - quotient * t);
+    }
+    if old_r < 0 {
+        (old_r, old_s, old_t) = (-old_r, -old_s, -old_t);
+    }
+    (old_r as i64, old_s as i64, old_t as i64)
+}
+
+/// Least common multiple, always non-negative.
+pub fn lcm(a: i64, b: i64) -> Result<i64, MathError> {
+    if a == 0 || b == 0 {
+        return Ok(0);
+    }
+    let divisor = i64::try_from(gcd(a, b)).map_err(|_| MathError::Overflow)?;
+    (a / divisor)
+        .checked_mul(b)
+        .and_then(i64::checked_abs)
+        .ok_or(MathError::Overflow)
+}
+
+/// The `x` in `0..modulus` with `a * x ≡ 1 (mod modulus)`, if `a` and `modulus` are coprime and
+/// `modulus` is positive.
+pub fn mod_inverse(a: i64, modulus: i64) -> Option<i64> {
+    if modulus <= 0 {
+        return None;
+    }
+    let (g, x, _) = extended_gcd(a, modulus);
+    (g == 1).then(|| x.rem_euclid(modulus))
+}
+
+/// Solves the system `x ≡ remainder (mod modulus)` for every `(remainder, modulus)` pair, moduli
+/// don't have to be coprime. Returns the smallest non-negative `x` and the modulus it repeats
+/// with, or `None` when the congruences contradict each other. Every modulus must be positive.
+pub fn crt(congruences: &[(i64, i64)]) -> Result<Option<(i64, i64)>, MathError> {
+    if congruences.iter().any(|(_, modulus)| *modulus <= 0) {
+        return Err(MathError::InvalidModulus);
+    }
+    let (mut x, mut modulus) = (0_i128, 1_i128);
+    for &(remainder, other) in congruences {
+        let other = other as i128;
+        let (g, p, _) = extended_gcd(modulus as i64, other as i64);
+        let (g, p) = (g as i128, p as i128);
+        let difference = remainder as i128 - x;
+        if difference % g != 0 {
+            return Ok(None);
+        }
+        // x + modulus * k ≡ remainder (mod other)
+        let step = other / g;
+        let k = ((difference / g) % step * p % step).rem_euclid(step);
+        x += modulus * k;
+        modulus *= step;
+        if modulus > i64::MAX as i128 {
+            return Err(MathError::Overflow);
+        }
+        x = x.rem_euclid(modulus);
+    }
+    Ok(Some((x as i64, modulus as i64)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_non_positive_moduli() {
+        assert_eq!(mod_inverse(3, 7), Some(5));
+        assert_eq!(mod_inverse(3, 0), None);
+        assert_eq!(mod_inverse(3, -7), None);
+        assert_eq!(crt(&[(2, 3), (3, 5)]), Ok(Some((8, 15))));
+        assert_eq!(crt(&[(2, 3), (1, 0)]), Err(MathError::InvalidModulus));
+        assert_eq!(crt(&[(1, -4)]), Err(MathError::InvalidModulus));
+    }
+
+    #[test]
+    fn test_number_theory() {
+        assert_eq!(extended_gcd(240, 46), (2, -9, 47));
+        assert_eq!(lcm(4, 6), Ok(12));
+        assert_eq!(lcm(-4, 6), Ok(12));
+        assert_eq!(gcd(-12, 18), 6);
+        assert_eq!(gcd(i64::MIN, 0), 1 << 63);
+        assert_eq!(lcm(i64::MIN, 1), Err(MathError::Overflow));
+        assert_eq!(lcm(i64::MIN, i64::MIN), Err(MathError::Overflow));
+        assert_eq!(mod_inverse(3, 11), Some(4));
+        assert_eq!(mod_inverse(4, 8), None);
+        assert_eq!(crt(&[(2, 3), (3, 5), (2, 7)]), Ok(Some((23, 105))));
+        assert_eq!(crt(&[(1, 4), (3, 6)]), Ok(Some((9, 12))));
+        assert_eq!(crt(&[(1, 4), (2, 6)]), Ok(None));
+    }
+}
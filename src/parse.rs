@@ -0,0 +1,191 @@
+use std::{fmt::Display, str::FromStr};
+
+/// A parse failure, `line` counts from 1 within the whole input.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    pub line: usize,
+    pub message: String,
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// A line of input that remembers where it came from, so errors can point back at it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Line<'a> {
+    pub number: usize,
+    pub text: &'a str,
+}
+
+impl<'a> Line<'a> {
+    pub fn error(&self, message: impl Into<String>) -> ParseError {
+        ParseError {
+            line: self.number,
+            message: message.into(),
+        }
+    }
+
+    fn parse<T: FromStr>(&self, value: &str) -> Result<T, ParseError> {
+        value
+            .trim()
+            .parse()
+            .map_err(|_| self.error(format!("could not parse {:?}", value.trim())))
+    }
+
+    /// Every signed integer in the line, ignoring whatever text surrounds them, so
+    /// `p=0,4 v=-3,3` gives `[0, 4, -3, 3]`.
+    pub fn integers<T: FromStr>(&self) -> Result<Vec<T>, ParseError> {
+        let bytes = self.text.as_bytes();
+        let mut integers = vec![];
+        let mut start = None;
+        for (i, byte) in bytes.iter().enumerate() {
+            let next_is_digit = bytes.get(i + 1).is_some_and(u8::is_ascii_digit);
+            match (start, byte) {
+                (None, b'-') if next_is_digit => start = Some(i),
+                (None, b'0'..=b'9') => start = Some(i),
+                (Some(_), b'0'..=b'9') => {}
+                (Some(from), _) => {
+                    integers.push(self.parse(&self.text[from..i])?);
+                    start = if *byte == b'-' && next_is_digit {
+                        Some(i)
+                    } else {
+                        None
+                    };
+                }
+                (None, _) => {}
+            }
+        }
+        if let Some(from) = start {
+            integers.push(self.parse(&self.text[from..])?);
+        }
+        Ok(integers)
+    }
+
+    /// Exactly `N` integers from the line, see `Line::integers`.
+    pub fn integers_n<T: FromStr, const N: usize>(&self) -> Result<[T; N], ParseError> {
+        let integers = self.integers()?;
+        let found = integers.len();
+        integers
+            .try_into()
+            .map_err(|_| self.error(format!("expected {} integers, found {}", N, found)))
+    }
+
+    /// Splits the line in two on `separator` and parses both halves, e.g. `47|53`.
+    pub fn pair<A: FromStr, B: FromStr>(&self, separator: &str) -> Result<(A, B), ParseError> {
+        let (left, right) = self
+            .text
+            .split_once(separator)
+            .ok_or_else(|| self.error(format!("expected {:?}", separator)))?;
+        Ok((self.parse(left)?, self.parse(right)?))
+    }
+
+    /// Parses every value between `separator`s, e.g. `75,47,61`.
+    pub fn separated<T: FromStr>(&self, separator: &str) -> Result<Vec<T>, ParseError> {
+        self.text
+            .split(separator)
+            .map(|value| self.parse(value))
+            .collect()
+    }
+
+    /// The value part of a `key: value` style record, checking the key matches.
+    pub fn field(&self, key: &str, separator: &str) -> Result<Line<'a>, ParseError> {
+        let (found, value) = self
+            .text
+            .split_once(separator)
+            .ok_or_else(|| self.error(format!("expected {:?}", separator)))?;
+        if found.trim() != key {
+            return Err(self.error(format!("expected key {:?}, found {:?}", key, found)));
+        }
+        Ok(Line {
+            number: self.number,
+            text: value.trim(),
+        })
+    }
+
+    /// Parses the value of a `key: value` style record, see `Line::field`.
+    pub fn value<T: FromStr>(&self, key: &str, separator: &str) -> Result<T, ParseError> {
+        let field = self.field(key, separator)?;
+        field.parse(field.text)
+    }
+}
+
+/// A block of consecutive non-blank lines.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Section<'a> {
+    lines: Vec<Line<'a>>,
+}
+
+impl<'a> Section<'a> {
+    pub fn lines(&self) -> &[Line<'a>] {
+        &self.lines
+    }
+
+    /// The line at `index`, or an error pointing at the end of the section.
+    pub fn line(&self, index: usize) -> Result<Line<'a>, ParseError> {
+        self.lines.get(index).copied().ok_or_else(|| ParseError {
+            line: self.lines.last().map_or(0, |line| line.number) + 1,
+            message: format!("expected at least {} lines in section", index + 1),
+        })
+    }
+
+    /// The section's lines joined back up, e.g. to hand a grid to another parser.
+    pub fn text(&self) -> String {
+        self.lines
+            .iter()
+            .map(|line| line.text)
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+/// Numbered lines of the input.
+pub fn lines(input: &str) -> impl Iterator<Item = Line<'_>> {
+    input.lines().enumerate().map(|(i, text)| Line {
+        number: i + 1,
+        text,
+    })
+}
+
+/// Splits the input on blank lines, dropping empty sections.
+pub fn sections(input: &str) -> Vec<Section<'_>> {
+    let mut sections = vec![];
+    let mut current = vec![];
+    for line in lines(input) {
+        if line.text.trim().is_empty() {
+            if !current.is_empty() {
+                sections.push(Section { lines: current });
+                current = vec![];
+            }
+        } else {
+            current.push(line);
+        }
+    }
+    if !current.is_empty() {
+        sections.push(Section { lines: current });
+    }
+    sections
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_helpers() {
+        let input = "p=0,4 v=-3,3\n\nRegister A: 729\n47|53\nx";
+        let parsed = lines(input).collect::<Vec<_>>();
+        assert_eq!(parsed[0].integers::<i32>(), Ok(vec![0, 4, -3, 3]));
+        assert_eq!(parsed[2].value::<u64>("Register A", ":"), Ok(729));
+        assert_eq!(parsed[3].pair::<u32, u32>("|"), Ok((47, 53)));
+        assert_eq!(parsed[4].integers_n::<u8, 1>().unwrap_err().line, 5);
+        let sections = sections(input);
+        assert_eq!(sections.len(), 2);
+        assert_eq!(sections[1].line(0).unwrap().number, 3);
+        assert_eq!(sections[1].line(3).unwrap_err().line, 6);
+    }
+}
@@ -0,0 +1,193 @@
+use std::{fmt::Display, str::FromStr};
+
+use itertools::Itertools;
+
+use super::{Combo, Computer, Instruction, VmError};
+
+impl FromStr for Combo {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "a" | "A" => Ok(Combo::A),
+            "b" | "B" => Ok(Combo::B),
+            "c" | "C" => Ok(Combo::C),
+            _ => match s.parse::<u8>() {
+                Ok(literal @ 0..=3) => Ok(Combo::Literal(literal)),
+                _ => Err(format!(
+                    "{:?} is not a combo operand, expected 0-3 or a, b, c",
+                    s
+                )),
+            },
+        }
+    }
+}
+
+fn literal(s: &str) -> Result<u8, String> {
+    match s.parse::<u8>() {
+        Ok(literal @ 0..=7) => Ok(literal),
+        _ => Err(format!("{:?} is not a literal operand, expected 0-7", s)),
+    }
+}
+
+impl FromStr for Instruction {
+    type Err = String;
+
+    /// Parses the mnemonics printed by the disassembler, e.g. `adv 3`, `out b` or `bxc`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts = s.split_whitespace();
+        let mnemonic = parts.next().ok_or("missing instruction")?;
+        let operand = parts.next();
+        if let Some(extra) = parts.next() {
+            return Err(format!("unexpected {:?} after operand", extra));
+        }
+        let required = || operand.ok_or(format!("{} needs an operand", mnemonic));
+        Ok(match mnemonic.to_ascii_lowercase().as_str() {
+            "adv" => Instruction::Adv(required()?.parse()?),
+            "bxl" => Instruction::Bxl(literal(required()?)?),
+            "bst" => Instruction::Bst(required()?.parse()?),
+            "jnz" => Instruction::Jnz(literal(required()?)?),
+            "bxc" => Instruction::Bxc(operand.map_or(Ok(0), literal)?),
+            "out" => Instruction::Out(required()?.parse()?),
+            "bdv" => Instruction::Bdv(required()?.parse()?),
+            "cdv" => Instruction::Cdv(required()?.parse()?),
+            _ => return Err(format!("unknown instruction {:?}", mnemonic)),
+        })
+    }
+}
+
+/// Assembles instructions separated by newlines or `;`, anything after a `#` is a comment.
+pub fn assemble(source: &str) -> Result<Vec<u8>, VmError> {
+    let mut builder = ProgramBuilder::new();
+    for (index, line) in source.lines().enumerate() {
+        let code = line.split('#').next().unwrap_or_default();
+        for statement in code.split(';').map(str::trim).filter(|s| !s.is_empty()) {
+            let instruction = statement.parse().map_err(|reason| VmError::Parse {
+                line: index + 1,
+                reason,
+            })?;
+            builder = builder.instruction(instruction);
+        }
+    }
+    builder.build()
+}
+
+/// Builds a program an instruction at a time, e.g.
+/// `ProgramBuilder::new().adv(Combo::Literal(3)).out(Combo::A).jnz(0).build()`. Operands are
+/// checked like the assembler checks them, `build` reports the first one that doesn't encode.
+#[derive(Debug, Clone, Default)]
+pub struct ProgramBuilder {
+    program: Vec<u8>,
+    error: Option<VmError>,
+}
+
+impl ProgramBuilder {
+    pub fn new() -> ProgramBuilder {
+        ProgramBuilder::default()
+    }
+
+    pub fn instruction(mut self, instruction: Instruction) -> ProgramBuilder {
+        let [opcode, operand] = instruction.encode();
+        // Literals above 7, or combo literals above 3, don't decode back to the same instruction.
+        match Instruction::decode(opcode, operand) {
+            Ok(decoded) if decoded == instruction => self.program.extend([opcode, operand]),
+            Ok(_) => {
+                self.error.get_or_insert(VmError::InvalidOperand(operand));
+            }
+            Err(error) => {
+                self.error.get_or_insert(error);
+            }
+        }
+        self
+    }
+
+    pub fn adv(self, combo: Combo) -> ProgramBuilder {
+        self.instruction(Instruction::Adv(combo))
+    }
+
+    pub fn bxl(self, literal: u8) -> ProgramBuilder {
+        self.instruction(Instruction::Bxl(literal))
+    }
+
+    pub fn bst(self, combo: Combo) -> ProgramBuilder {
+        self.instruction(Instruction::Bst(combo))
+    }
+
+    pub fn jnz(self, literal: u8) -> ProgramBuilder {
+        self.instruction(Instruction::Jnz(literal))
+    }
+
+    pub fn bxc(self) -> ProgramBuilder {
+        self.instruction(Instruction::Bxc(0))
+    }
+
+    pub fn out(self, combo: Combo) -> ProgramBuilder {
+        self.instruction(Instruction::Out(combo))
+    }
+
+    pub fn bdv(self, combo: Combo) -> ProgramBuilder {
+        self.instruction(Instruction::Bdv(combo))
+    }
+
+    pub fn cdv(self, combo: Combo) -> ProgramBuilder {
+        self.instruction(Instruction::Cdv(combo))
+    }
+
+    pub fn build(self) -> Result<Vec<u8>, VmError> {
+        match self.error {
+            Some(error) => Err(error),
+            None => Ok(self.program),
+        }
+    }
+}
+
+/// Prints the computer in the puzzle's input format, which `Computer::parse` reads back.
+impl Display for Computer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "Register A: {}", self.register_a)?;
+        writeln!(f, "Register B: {}", self.register_b)?;
+        writeln!(f, "Register C: {}", self.register_c)?;
+        writeln!(f)?;
+        write!(f, "Program: {}", self.program.iter().join(","))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vm::disassemble;
+
+    const EXAMPLE: &str = "Register A: 729\nRegister B: 0\nRegister C: 0\n\nProgram: 0,1,5,4,3,0\n";
+
+    #[test]
+    fn test_assembler_round_trip() {
+        let computer = Computer::parse(EXAMPLE).unwrap();
+        assert_eq!(
+            Computer::parse(&computer.to_string()).unwrap().program,
+            computer.program
+        );
+        assert_eq!(computer.to_string(), EXAMPLE.trim_end());
+        let source = disassemble(&computer.program).unwrap();
+        assert_eq!(assemble(&source).unwrap(), computer.program);
+        assert_eq!(
+            assemble("adv 3 # shift\nout b; bxc 2"),
+            Ok(vec![0, 3, 5, 5, 4, 2])
+        );
+        assert!(matches!(
+            assemble("adv 1\nout 7"),
+            Err(VmError::Parse { line: 2, .. })
+        ));
+        assert_eq!(
+            ProgramBuilder::new().bxl(8).jnz(0).build(),
+            Err(VmError::InvalidOperand(8))
+        );
+        assert_eq!(
+            ProgramBuilder::new().jnz(9).build(),
+            Err(VmError::InvalidOperand(9))
+        );
+        assert_eq!(
+            ProgramBuilder::new().out(Combo::Literal(5)).build(),
+            Err(VmError::InvalidOperand(5))
+        );
+    }
+}
@@ -0,0 +1,430 @@
+use std::{collections::HashSet, fmt::Display};
+
+use itertools::Itertools;
+
+use crate::parse::{self, ParseError};
+
+mod asm;
+mod search;
+
+pub use asm::*;
+pub use search::*;
+
+/// The operand of an instruction that reads a combo value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Combo {
+    Literal(u8),
+    A,
+    B,
+    C,
+}
+
+impl Combo {
+    pub fn decode(operand: u8) -> Result<Combo, VmError> {
+        match operand {
+            literal @ 0..=3 => Ok(Combo::Literal(literal)),
+            4 => Ok(Combo::A),
+            5 => Ok(Combo::B),
+            6 => Ok(Combo::C),
+            7 => Err(VmError::ReservedCombo),
+            _ => Err(VmError::InvalidOperand(operand)),
+        }
+    }
+
+    pub fn encode(&self) -> u8 {
+        match self {
+            Combo::Literal(literal) => *literal,
+            Combo::A => 4,
+            Combo::B => 5,
+            Combo::C => 6,
+        }
+    }
+}
+
+impl Display for Combo {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Combo::Literal(literal) => write!(f, "{}", literal),
+            Combo::A => write!(f, "a"),
+            Combo::B => write!(f, "b"),
+            Combo::C => write!(f, "c"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Instruction {
+    Adv(Combo),
+    Bxl(u8),
+    Bst(Combo),
+    Jnz(u8),
+    /// Reads and ignores its operand, which is kept so programs round trip.
+    Bxc(u8),
+    Out(Combo),
+    Bdv(Combo),
+    Cdv(Combo),
+}
+
+impl Instruction {
+    pub fn decode(opcode: u8, operand: u8) -> Result<Instruction, VmError> {
+        if operand > 7 {
+            return Err(VmError::InvalidOperand(operand));
+        }
+        Ok(match opcode {
+            0 => Instruction::Adv(Combo::decode(operand)?),
+            1 => Instruction::Bxl(operand),
+            2 => Instruction::Bst(Combo::decode(operand)?),
+            3 => Instruction::Jnz(operand),
+            4 => Instruction::Bxc(operand),
+            5 => Instruction::Out(Combo::decode(operand)?),
+            6 => Instruction::Bdv(Combo::decode(operand)?),
+            7 => Instruction::Cdv(Combo::decode(operand)?),
+            _ => return Err(VmError::InvalidOpcode(opcode)),
+        })
+    }
+
+    pub fn encode(&self) -> [u8; 2] {
+        match self {
+            Instruction::Adv(combo) => [0, combo.encode()],
+            Instruction::Bxl(literal) => [1, *literal],
+            Instruction::Bst(combo) => [2, combo.encode()],
+            Instruction::Jnz(literal) => [3, *literal],
+            Instruction::Bxc(ignored) => [4, *ignored],
+            Instruction::Out(combo) => [5, combo.encode()],
+            Instruction::Bdv(combo) => [6, combo.encode()],
+            Instruction::Cdv(combo) => [7, combo.encode()],
+        }
+    }
+}
+
+impl Display for Instruction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Instruction::Adv(combo) => write!(f, "adv {}", combo),
+            Instruction::Bxl(literal) => write!(f, "bxl {}", literal),
+            Instruction::Bst(combo) => write!(f, "bst {}", combo),
+            Instruction::Jnz(literal) => write!(f, "jnz {}", literal),
+            Instruction::Bxc(0) => write!(f, "bxc"),
+            Instruction::Bxc(ignored) => write!(f, "bxc {}", ignored),
+            Instruction::Out(combo) => write!(f, "out {}", combo),
+            Instruction::Bdv(combo) => write!(f, "bdv {}", combo),
+            Instruction::Cdv(combo) => write!(f, "cdv {}", combo),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VmError {
+    InvalidOpcode(u8),
+    InvalidOperand(u8),
+    /// Combo operand 7 is reserved and never appears in valid programs.
+    ReservedCombo,
+    /// A program of odd length ends with an opcode that has no operand.
+    MissingOperand {
+        pointer: usize,
+    },
+    CycleLimit(usize),
+    UnsupportedProgram(String),
+    Parse {
+        line: usize,
+        reason: String,
+    },
+}
+
+impl Display for VmError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            VmError::InvalidOpcode(opcode) => write!(f, "opcode {} is not recognised", opcode),
+            VmError::InvalidOperand(operand) => write!(f, "operand {} is not 3 bits", operand),
+            VmError::ReservedCombo => write!(f, "combo operand 7 is reserved"),
+            VmError::MissingOperand { pointer } => {
+                write!(f, "opcode at {} has no operand", pointer)
+            }
+            VmError::CycleLimit(limit) => write!(f, "program ran for more than {} cycles", limit),
+            VmError::UnsupportedProgram(reason) => write!(f, "unsupported program: {}", reason),
+            VmError::Parse { line, reason } => write!(f, "line {}: {}", line, reason),
+        }
+    }
+}
+
+impl std::error::Error for VmError {}
+
+impl From<ParseError> for VmError {
+    fn from(error: ParseError) -> Self {
+        VmError::Parse {
+            line: error.line,
+            reason: error.message,
+        }
+    }
+}
+
+/// Decodes a whole program, one instruction per opcode and operand pair.
+pub fn decode_program(program: &[u8]) -> Result<Vec<Instruction>, VmError> {
+    program
+        .chunks(2)
+        .enumerate()
+        .map(|(index, pair)| match pair {
+            [opcode, operand] => Instruction::decode(*opcode, *operand),
+            _ => Err(VmError::MissingOperand { pointer: index * 2 }),
+        })
+        .collect()
+}
+
+/// Prints a program as one mnemonic per line, e.g. `adv 3` or `out b`.
+pub fn disassemble(program: &[u8]) -> Result<String, VmError> {
+    Ok(decode_program(program)?.iter().join("\n"))
+}
+
+/// A single executed instruction and the machine state before it ran.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TraceStep {
+    pub pointer: usize,
+    pub instruction: Instruction,
+    pub registers: [u64; 3],
+    pub output: Option<u8>,
+}
+
+impl Display for TraceStep {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let [a, b, c] = self.registers;
+        write!(
+            f,
+            "{:>3}: {:<6} a={} b={} c={}",
+            self.pointer,
+            self.instruction.to_string(),
+            a,
+            b,
+            c
+        )?;
+        if let Some(output) = self.output {
+            write!(f, " -> {}", output)?;
+        }
+        Ok(())
+    }
+}
+
+/// Why `Computer::run` stopped without an error.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Halt {
+    /// The instruction pointer moved past the end of the program.
+    Finished,
+    /// The instruction pointer reached a breakpoint, the instruction there hasn't run yet.
+    Breakpoint(usize),
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Computer {
+    pub register_a: u64,
+    pub register_b: u64,
+    pub register_c: u64,
+    pub program: Vec<u8>,
+    pub output: Vec<u8>,
+    pub instruction_pointer: usize,
+    cycles: usize,
+    cycle_limit: Option<usize>,
+    breakpoints: HashSet<usize>,
+    trace: Option<Vec<TraceStep>>,
+}
+
+impl Computer {
+    pub fn new(register_a: u64, register_b: u64, register_c: u64, program: Vec<u8>) -> Computer {
+        Computer {
+            register_a,
+            register_b,
+            register_c,
+            program,
+            ..Default::default()
+        }
+    }
+
+    /// Parses the puzzle's `Register A: ..` block followed by a `Program: ..` line.
+    pub fn parse(input: &str) -> Result<Computer, VmError> {
+        let sections = parse::sections(input);
+        let registers = sections.first().ok_or(ParseError {
+            line: 1,
+            message: String::from("registers not found"),
+        })?;
+        let register_a = registers.line(0)?.value("Register A", ":")?;
+        let register_b = registers.line(1)?.value("Register B", ":")?;
+        let register_c = registers.line(2)?.value("Register C", ":")?;
+        let program = sections
+            .get(1)
+            .ok_or(ParseError {
+                line: registers.line(2)?.number + 1,
+                message: String::from("program not found"),
+            })?
+            .line(0)?
+            .field("Program", ":")?
+            .separated(",")?;
+        Ok(Computer::new(register_a, register_b, register_c, program))
+    }
+
+    /// Clears output and execution state and loads new register values, keeping the program,
+    /// breakpoints and cycle limit.
+    pub fn reset(&mut self, register_a: u64, register_b: u64, register_c: u64) {
+        self.register_a = register_a;
+        self.register_b = register_b;
+        self.register_c = register_c;
+        self.output.clear();
+        self.instruction_pointer = 0;
+        self.cycles = 0;
+        if let Some(trace) = self.trace.as_mut() {
+            trace.clear();
+        }
+    }
+
+    pub fn set_cycle_limit(&mut self, limit: Option<usize>) {
+        self.cycle_limit = limit;
+    }
+
+    pub fn add_breakpoint(&mut self, pointer: usize) {
+        self.breakpoints.insert(pointer);
+    }
+
+    pub fn remove_breakpoint(&mut self, pointer: usize) {
+        self.breakpoints.remove(&pointer);
+    }
+
+    /// Starts recording every executed instruction, see `Computer::trace`.
+    pub fn enable_trace(&mut self) {
+        self.trace.get_or_insert_with(Vec::new);
+    }
+
+    pub fn trace(&self) -> &[TraceStep] {
+        self.trace.as_deref().unwrap_or_default()
+    }
+
+    pub fn cycles(&self) -> usize {
+        self.cycles
+    }
+
+    pub fn output_string(&self) -> String {
+        self.output.iter().join(",")
+    }
+
+    pub fn is_halted(&self) -> bool {
+        self.instruction_pointer + 1 >= self.program.len()
+    }
+
+    /// The instruction the pointer is on, `None` once the program has halted.
+    pub fn current(&self) -> Result<Option<Instruction>, VmError> {
+        if self.is_halted() {
+            return Ok(None);
+        }
+        let opcode = self.program[self.instruction_pointer];
+        let operand = self.program[self.instruction_pointer + 1];
+        Instruction::decode(opcode, operand).map(Some)
+    }
+
+    fn combo(&self, combo: Combo) -> u64 {
+        match combo {
+            Combo::Literal(literal) => literal as u64,
+            Combo::A => self.register_a,
+            Combo::B => self.register_b,
+            Combo::C => self.register_c,
+        }
+    }
+
+    fn divide(&self, combo: Combo) -> u64 {
+        let shift = self.combo(combo);
+        u32::try_from(shift)
+            .ok()
+            .and_then(|shift| self.register_a.checked_shr(shift))
+            .unwrap_or(0)
+    }
+
+    /// Executes one instruction, returning it or `None` if the program had already halted.
+    pub fn step(&mut self) -> Result<Option<Instruction>, VmError> {
+        let Some(instruction) = self.current()? else {
+            return Ok(None);
+        };
+        if self.cycle_limit.is_some_and(|limit| self.cycles >= limit) {
+            return Err(VmError::CycleLimit(self.cycles));
+        }
+        let pointer = self.instruction_pointer;
+        let registers = [self.register_a, self.register_b, self.register_c];
+        let mut output = None;
+        self.instruction_pointer += 2;
+        match instruction {
+            Instruction::Adv(combo) => self.register_a = self.divide(combo),
+            Instruction::Bxl(literal) => self.register_b ^= literal as u64,
+            Instruction::Bst(combo) => self.register_b = self.combo(combo) % 8,
+            Instruction::Jnz(literal) => {
+                if self.register_a != 0 {
+                    self.instruction_pointer = literal as usize;
+                }
+            }
+            Instruction::Bxc(_) => self.register_b ^= self.register_c,
+            Instruction::Out(combo) => {
+                let value = (self.combo(combo) % 8) as u8;
+                self.output.push(value);
+                output = Some(value);
+            }
+            Instruction::Bdv(combo) => self.register_b = self.divide(combo),
+            Instruction::Cdv(combo) => self.register_c = self.divide(combo),
+        }
+        self.cycles += 1;
+        if let Some(trace) = self.trace.as_mut() {
+            trace.push(TraceStep {
+                pointer,
+                instruction,
+                registers,
+                output,
+            });
+        }
+        Ok(Some(instruction))
+    }
+
+    /// Runs until the program halts or reaches a breakpoint. The instruction under the pointer
+    /// always runs first, so calling `run` again continues past the breakpoint it stopped on.
+    pub fn run(&mut self) -> Result<Halt, VmError> {
+        if self.step()?.is_none() {
+            return Ok(Halt::Finished);
+        }
+        loop {
+            if self.breakpoints.contains(&self.instruction_pointer) && !self.is_halted() {
+                return Ok(Halt::Breakpoint(self.instruction_pointer));
+            }
+            if self.step()?.is_none() {
+                return Ok(Halt::Finished);
+            }
+        }
+    }
+
+    /// Runs to completion, ignoring breakpoints, and returns the output.
+    pub fn execute(&mut self) -> Result<&[u8], VmError> {
+        while self.step()?.is_some() {}
+        Ok(&self.output)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_disassemble() {
+        let program = disassemble(&[0, 3, 5, 5, 4, 0, 3, 0]).unwrap();
+        assert_eq!(program, "adv 3\nout b\nbxc\njnz 0");
+        assert_eq!(disassemble(&[2, 7]), Err(VmError::ReservedCombo));
+    }
+
+    #[test]
+    fn test_breakpoints_and_trace() {
+        let mut computer = Computer::new(2024, 0, 0, vec![0, 1, 5, 4, 3, 0]);
+        computer.enable_trace();
+        computer.add_breakpoint(2);
+        assert_eq!(computer.run(), Ok(Halt::Breakpoint(2)));
+        assert_eq!(computer.register_a, 1012);
+        assert_eq!(computer.run(), Ok(Halt::Breakpoint(2)));
+        assert_eq!(computer.output, vec![4]);
+        assert_eq!(
+            computer.trace()[1].to_string(),
+            "  2: out a  a=1012 b=0 c=0 -> 4"
+        );
+
+        computer.reset(2024, 0, 0);
+        computer.set_cycle_limit(Some(5));
+        assert_eq!(computer.execute(), Err(VmError::CycleLimit(5)));
+    }
+}
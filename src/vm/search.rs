@@ -0,0 +1,165 @@
+use super::{decode_program, Combo, Computer, Instruction, VmError};
+
+const CYCLES_PER_OUTPUT: usize = 1_000;
+
+/// Checks `program` has the shape the reverse search relies on: a single loop ending in `jnz 0`
+/// that shifts register A right by 3 bits with one `adv 3`, writes no other register A value and
+/// outputs at least once. Registers B and C must be written before they are read in the loop, so nothing but A carries
+/// over from one loop to the next.
+fn check_shape(program: &[u8]) -> Result<(), VmError> {
+    let instructions = decode_program(program)?;
+    let unsupported = |reason: &str| Err(VmError::UnsupportedProgram(reason.to_string()));
+    if instructions.last() != Some(&Instruction::Jnz(0)) {
+        return unsupported("program must end with jnz 0");
+    }
+    let jumps = instructions
+        .iter()
+        .filter(|instruction| matches!(instruction, Instruction::Jnz(_)))
+        .count();
+    if jumps != 1 {
+        return unsupported("program must loop with a single jnz");
+    }
+    let shifts = instructions
+        .iter()
+        .filter(|instruction| matches!(instruction, Instruction::Adv(_)))
+        .collect::<Vec<_>>();
+    if shifts != [&Instruction::Adv(Combo::Literal(3))] {
+        return unsupported("program must shift a with exactly one adv 3");
+    }
+    if !instructions
+        .iter()
+        .any(|instruction| matches!(instruction, Instruction::Out(_)))
+    {
+        return unsupported("program must output in every loop");
+    }
+    let (mut written_b, mut written_c) = (false, false);
+    for instruction in &instructions {
+        let combo = match instruction {
+            Instruction::Adv(combo)
+            | Instruction::Bst(combo)
+            | Instruction::Out(combo)
+            | Instruction::Bdv(combo)
+            | Instruction::Cdv(combo) => Some(*combo),
+            Instruction::Bxl(_) | Instruction::Jnz(_) | Instruction::Bxc(_) => None,
+        };
+        let reads_b = matches!(instruction, Instruction::Bxl(_) | Instruction::Bxc(_))
+            || combo == Some(Combo::B);
+        let reads_c = matches!(instruction, Instruction::Bxc(_)) || combo == Some(Combo::C);
+        if (reads_b && !written_b) || (reads_c && !written_c) {
+            return unsupported("program must write b and c before reading them in each loop");
+        }
+        written_b |= matches!(
+            instruction,
+            Instruction::Bxl(_) | Instruction::Bst(_) | Instruction::Bxc(_) | Instruction::Bdv(_)
+        );
+        written_c |= matches!(instruction, Instruction::Cdv(_));
+    }
+    Ok(())
+}
+
+/// Finds the smallest register A for which the computer's program outputs `target`.
+///
+/// Each loop consumes the low 3 bits of A, so the output for A is what the first loop outputs followed
+/// by the output for `A >> 3`. The search builds A an octal digit at a time from the most significant
+/// end, keeping every candidate whose output is a suffix of the target, and returns the smallest
+/// candidate of the first length that produces the target in full.
+pub fn find_register_a(computer: &Computer, target: &[u8]) -> Result<Option<u64>, VmError> {
+    check_shape(&computer.program)?;
+    let mut computer = computer.clone();
+    let (register_b, register_c) = (computer.register_b, computer.register_c);
+    computer.set_cycle_limit(Some(CYCLES_PER_OUTPUT * target.len()));
+    let mut candidates: Vec<u64> = vec![0];
+    // Every loop outputs at least once, so A never needs more octal digits than `target` has values.
+    for _ in 0..target.len() {
+        let mut next = Vec::with_capacity(candidates.len() * 8);
+        let mut found: Option<u64> = None;
+        for prefix in &candidates {
+            for digit in 0..8 {
+                let Some(register_a) = prefix.checked_mul(8).map(|a| a + digit) else {
+                    continue;
+                };
+                if register_a == 0 {
+                    continue;
+                }
+                computer.reset(register_a, register_b, register_c);
+                let output = computer.execute()?;
+                if output == target {
+                    found = Some(found.map_or(register_a, |best| best.min(register_a)));
+                } else if output.len() < target.len() && target.ends_with(output) {
+                    next.push(register_a);
+                }
+            }
+        }
+        if found.is_some() {
+            return Ok(found);
+        }
+        if next.is_empty() {
+            return Ok(None);
+        }
+        candidates = next;
+    }
+    Ok(None)
+}
+
+/// Finds the smallest register A that makes the program output a copy of itself.
+pub fn find_quine(computer: &Computer) -> Result<Option<u64>, VmError> {
+    find_register_a(computer, &computer.program)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vm::ProgramBuilder;
+
+    #[test]
+    fn test_find_register_a() {
+        let program = ProgramBuilder::new()
+            .bst(Combo::A)
+            .bxl(5)
+            .cdv(Combo::B)
+            .bxl(6)
+            .adv(Combo::Literal(3))
+            .bxc()
+            .out(Combo::B)
+            .jnz(0)
+            .build()
+            .unwrap();
+        let computer = Computer::new(0, 0, 0, program);
+        let target = [2, 4, 1, 5];
+        let register_a = find_register_a(&computer, &target).unwrap().unwrap();
+        let mut check = computer.clone();
+        check.reset(register_a, 0, 0);
+        assert_eq!(check.execute().unwrap(), &target);
+        assert!((1..register_a).all(|a| {
+            check.reset(a, 0, 0);
+            check.execute().unwrap() != target
+        }));
+        assert!(matches!(
+            find_register_a(&Computer::new(0, 0, 0, vec![5, 4, 3, 0]), &[1]),
+            Err(VmError::UnsupportedProgram(_))
+        ));
+        // B is read before anything in the loop writes it, so it carries over between loops.
+        let stale = ProgramBuilder::new()
+            .bxl(1)
+            .out(Combo::B)
+            .adv(Combo::Literal(3))
+            .jnz(0)
+            .build()
+            .unwrap();
+        assert!(matches!(
+            find_register_a(&Computer::new(0, 0, 0, stale), &[1]),
+            Err(VmError::UnsupportedProgram(_))
+        ));
+        // Without an output every candidate would match the empty suffix.
+        let silent = ProgramBuilder::new()
+            .bst(Combo::A)
+            .adv(Combo::Literal(3))
+            .jnz(0)
+            .build()
+            .unwrap();
+        assert!(matches!(
+            find_register_a(&Computer::new(0, 0, 0, silent), &[1, 2, 3]),
+            Err(VmError::UnsupportedProgram(_))
+        ));
+    }
+}